@@ -0,0 +1,4 @@
+// Recompila quando as migrations mudarem, já que elas são embutidas pelo sqlx::migrate!
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,52 @@
+-- Esquema inicial com as tabelas usadas pelos handlers.
+-- Usa IF NOT EXISTS para poder rodar sobre bancos que já foram criados manualmente.
+
+CREATE TABLE IF NOT EXISTS public.users (
+    id         SERIAL PRIMARY KEY,
+    first_name VARCHAR(100) NOT NULL,
+    last_name  VARCHAR(100) NOT NULL,
+    city       VARCHAR(100) NOT NULL DEFAULT ''
+);
+
+CREATE TABLE IF NOT EXISTS public.habilidade (
+    id   SERIAL PRIMARY KEY,
+    nome VARCHAR(100) NOT NULL,
+    CONSTRAINT habilidade_nome_key UNIQUE (nome)
+);
+
+CREATE TABLE IF NOT EXISTS public.sub_habilidade (
+    id            SERIAL PRIMARY KEY,
+    id_habilidade INTEGER NOT NULL REFERENCES public.habilidade (id),
+    nome          VARCHAR(100) NOT NULL,
+    CONSTRAINT sub_habilidade_habilidade_nome_key UNIQUE (id_habilidade, nome)
+);
+
+CREATE TABLE IF NOT EXISTS public.usuario_sub_habilidade (
+    id_users          INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_sub_habilidade INTEGER NOT NULL REFERENCES public.sub_habilidade (id),
+    descricao         TEXT NOT NULL,
+    valor             DOUBLE PRECISION NOT NULL,
+    created_at        TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (id_users, id_sub_habilidade)
+);
+
+CREATE INDEX IF NOT EXISTS usuario_sub_habilidade_sub_habilidade_idx
+    ON public.usuario_sub_habilidade (id_sub_habilidade);
+
+CREATE TABLE IF NOT EXISTS public.teste_match (
+    id          SERIAL PRIMARY KEY,
+    id_deu_like INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_liked    INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    match       BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS teste_match_id_liked_idx ON public.teste_match (id_liked);
+CREATE INDEX IF NOT EXISTS teste_match_id_deu_like_idx ON public.teste_match (id_deu_like);
+
+CREATE TABLE IF NOT EXISTS public.historico_match (
+    id         SERIAL PRIMARY KEY,
+    id1        INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id2        INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    CONSTRAINT historico_match_id1_id2_key UNIQUE (id1, id2)
+);
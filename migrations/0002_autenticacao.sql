@@ -0,0 +1,8 @@
+-- Credenciais para login/registro. Usuários antigos ficam sem e-mail até se registrarem.
+
+ALTER TABLE public.users
+    ADD COLUMN IF NOT EXISTS email         VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS password_hash TEXT,
+    ADD COLUMN IF NOT EXISTS created_at    TIMESTAMP NOT NULL DEFAULT now();
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_key ON public.users (lower(email));
@@ -0,0 +1,8 @@
+-- Papéis de acesso por usuário: user (padrão), moderator e admin.
+
+ALTER TABLE public.users
+    ADD COLUMN IF NOT EXISTS role VARCHAR(20) NOT NULL DEFAULT 'user';
+
+ALTER TABLE public.users
+    DROP CONSTRAINT IF EXISTS users_role_check,
+    ADD CONSTRAINT users_role_check CHECK (role IN ('user', 'moderator', 'admin'));
@@ -0,0 +1,26 @@
+-- Remove likes duplicados (mantém a linha com match, ou a mais antiga) e impede novos duplicados.
+
+DELETE FROM public.teste_match t
+USING public.teste_match o
+WHERE t.id_deu_like = o.id_deu_like
+  AND t.id_liked = o.id_liked
+  AND (o.match, -o.id) > (t.match, -t.id);
+
+ALTER TABLE public.teste_match
+    DROP CONSTRAINT IF EXISTS teste_match_id_deu_like_id_liked_key,
+    ADD CONSTRAINT teste_match_id_deu_like_id_liked_key UNIQUE (id_deu_like, id_liked);
+
+-- Respostas guardadas para o header Idempotency-Key (POST/PUT).
+-- status/corpo ficam NULL enquanto a requisição original ainda está sendo processada.
+CREATE TABLE IF NOT EXISTS public.idempotency_key (
+    escopo       VARCHAR(64) NOT NULL,
+    chave        VARCHAR(255) NOT NULL,
+    fingerprint  CHAR(64) NOT NULL,
+    status       SMALLINT,
+    corpo        BYTEA,
+    content_type TEXT,
+    created_at   TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (escopo, chave)
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_key_created_at_idx ON public.idempotency_key (created_at);
@@ -0,0 +1,18 @@
+-- Sub-habilidades que o usuário quer aprender, em paralelo às que ele oferece.
+
+CREATE TABLE IF NOT EXISTS public.usuario_sub_habilidade_desejada (
+    id_users          INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_sub_habilidade INTEGER NOT NULL REFERENCES public.sub_habilidade (id),
+    descricao         TEXT NOT NULL DEFAULT '',
+    nivel_desejado    VARCHAR(20) NOT NULL,
+    prioridade        SMALLINT NOT NULL DEFAULT 3,
+    created_at        TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (id_users, id_sub_habilidade),
+    CONSTRAINT usuario_sub_habilidade_desejada_nivel_check
+        CHECK (nivel_desejado IN ('iniciante', 'intermediario', 'avancado')),
+    CONSTRAINT usuario_sub_habilidade_desejada_prioridade_check
+        CHECK (prioridade BETWEEN 1 AND 5)
+);
+
+CREATE INDEX IF NOT EXISTS usuario_sub_habilidade_desejada_sub_habilidade_idx
+    ON public.usuario_sub_habilidade_desejada (id_sub_habilidade);
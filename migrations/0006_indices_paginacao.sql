@@ -0,0 +1,8 @@
+-- Índices para a paginação por cursor de /obter e /obter_tudo: cada ordenação
+-- termina em (id_users, id_sub_habilidade) para que a chave seja única.
+
+CREATE INDEX IF NOT EXISTS usuario_sub_habilidade_created_at_idx
+    ON public.usuario_sub_habilidade (created_at, id_users, id_sub_habilidade);
+
+CREATE INDEX IF NOT EXISTS usuario_sub_habilidade_valor_idx
+    ON public.usuario_sub_habilidade (valor, id_users, id_sub_habilidade);
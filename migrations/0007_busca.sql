@@ -0,0 +1,34 @@
+-- Busca textual em português sobre as ofertas (/buscar): full-text com stemming
+-- e sem acentos, mais similaridade por trigramas para erros de digitação.
+
+CREATE EXTENSION IF NOT EXISTS unaccent SCHEMA public;
+CREATE EXTENSION IF NOT EXISTS pg_trgm SCHEMA public;
+
+-- unaccent() é STABLE e não pode ser usada em índices; o wrapper fixa o dicionário
+CREATE OR REPLACE FUNCTION public.f_unaccent(text)
+RETURNS text
+LANGUAGE sql IMMUTABLE PARALLEL SAFE STRICT
+AS $$ SELECT public.unaccent('public.unaccent'::regdictionary, $1) $$;
+
+-- Configuração "portuguese" que também remove acentos: "violao" encontra "Violão"
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'portugues_sem_acento') THEN
+        CREATE TEXT SEARCH CONFIGURATION public.portugues_sem_acento (COPY = pg_catalog.portuguese);
+        ALTER TEXT SEARCH CONFIGURATION public.portugues_sem_acento
+            ALTER MAPPING FOR hword, hword_part, word WITH public.unaccent, portuguese_stem;
+    END IF;
+END
+$$;
+
+CREATE INDEX IF NOT EXISTS usuario_sub_habilidade_descricao_fts_idx
+    ON public.usuario_sub_habilidade
+    USING gin (to_tsvector('public.portugues_sem_acento'::regconfig, descricao));
+
+CREATE INDEX IF NOT EXISTS usuario_sub_habilidade_descricao_trgm_idx
+    ON public.usuario_sub_habilidade
+    USING gin (public.f_unaccent(lower(descricao)) public.gin_trgm_ops);
+
+CREATE INDEX IF NOT EXISTS sub_habilidade_nome_trgm_idx
+    ON public.sub_habilidade
+    USING gin (public.f_unaccent(lower(nome)) public.gin_trgm_ops);
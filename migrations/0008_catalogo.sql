@@ -0,0 +1,36 @@
+-- Marca de tempo da última alteração do catálogo (/catalogo), usada no Last-Modified.
+-- As contagens de ofertas também mudam com DELETE em usuario_sub_habilidade,
+-- que não deixa rastro em colunas updated_at; por isso o registro é mantido por triggers.
+
+CREATE TABLE IF NOT EXISTS public.catalogo_estado (
+    id            BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    atualizado_em TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+INSERT INTO public.catalogo_estado (id) VALUES (TRUE) ON CONFLICT DO NOTHING;
+
+CREATE OR REPLACE FUNCTION public.tocar_catalogo()
+RETURNS trigger
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    UPDATE public.catalogo_estado SET atualizado_em = now();
+    RETURN NULL;
+END
+$$;
+
+DROP TRIGGER IF EXISTS habilidade_tocar_catalogo ON public.habilidade;
+CREATE TRIGGER habilidade_tocar_catalogo
+    AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON public.habilidade
+    FOR EACH STATEMENT EXECUTE FUNCTION public.tocar_catalogo();
+
+DROP TRIGGER IF EXISTS sub_habilidade_tocar_catalogo ON public.sub_habilidade;
+CREATE TRIGGER sub_habilidade_tocar_catalogo
+    AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON public.sub_habilidade
+    FOR EACH STATEMENT EXECUTE FUNCTION public.tocar_catalogo();
+
+-- Só mudanças que alteram as contagens: INSERT, DELETE e troca de sub-habilidade
+DROP TRIGGER IF EXISTS usuario_sub_habilidade_tocar_catalogo ON public.usuario_sub_habilidade;
+CREATE TRIGGER usuario_sub_habilidade_tocar_catalogo
+    AFTER INSERT OR DELETE OR UPDATE OF id_sub_habilidade OR TRUNCATE ON public.usuario_sub_habilidade
+    FOR EACH STATEMENT EXECUTE FUNCTION public.tocar_catalogo();
@@ -0,0 +1,8 @@
+-- Desativação lógica de entradas do catálogo: somem dos seletores, mas as ofertas
+-- e sub-habilidades desejadas que já apontam para elas continuam válidas.
+
+ALTER TABLE public.habilidade
+    ADD COLUMN IF NOT EXISTS ativo BOOLEAN NOT NULL DEFAULT TRUE;
+
+ALTER TABLE public.sub_habilidade
+    ADD COLUMN IF NOT EXISTS ativo BOOLEAN NOT NULL DEFAULT TRUE;
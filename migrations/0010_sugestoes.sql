@@ -0,0 +1,34 @@
+-- Sugestões de novas sub-habilidades feitas pelos usuários, com fila de moderação.
+
+CREATE TABLE IF NOT EXISTS public.sugestao_sub_habilidade (
+    id                SERIAL PRIMARY KEY,
+    id_users          INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_habilidade     INTEGER NOT NULL REFERENCES public.habilidade (id),
+    nome              VARCHAR(100) NOT NULL,
+    justificativa     TEXT NOT NULL DEFAULT '',
+    -- Oferta que o autor quer cadastrar quando a sub-habilidade existir (opcional)
+    descricao_oferta  TEXT,
+    valor_oferta      DOUBLE PRECISION,
+    status            VARCHAR(20) NOT NULL DEFAULT 'pendente',
+    -- Sub-habilidade criada na aprovação ou existente na mesclagem
+    id_sub_habilidade INTEGER REFERENCES public.sub_habilidade (id) ON DELETE SET NULL,
+    motivo            TEXT,
+    id_moderador      INTEGER REFERENCES public.users (id) ON DELETE SET NULL,
+    created_at        TIMESTAMP NOT NULL DEFAULT now(),
+    decidida_em       TIMESTAMP,
+    CONSTRAINT sugestao_sub_habilidade_status_check
+        CHECK (status IN ('pendente', 'aprovada', 'rejeitada', 'mesclada')),
+    CONSTRAINT sugestao_sub_habilidade_oferta_check
+        CHECK ((descricao_oferta IS NULL) = (valor_oferta IS NULL))
+);
+
+-- Uma única sugestão pendente por nome (sem maiúsculas/acentos) em cada habilidade
+CREATE UNIQUE INDEX IF NOT EXISTS sugestao_sub_habilidade_pendente_key
+    ON public.sugestao_sub_habilidade (id_habilidade, public.f_unaccent(lower(nome)))
+    WHERE status = 'pendente';
+
+CREATE INDEX IF NOT EXISTS sugestao_sub_habilidade_status_idx
+    ON public.sugestao_sub_habilidade (status, id);
+
+CREATE INDEX IF NOT EXISTS sugestao_sub_habilidade_id_users_idx
+    ON public.sugestao_sub_habilidade (id_users);
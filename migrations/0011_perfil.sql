@@ -0,0 +1,7 @@
+-- Perfil público do usuário: bio e escolha do que outros usuários podem ver.
+
+ALTER TABLE public.users
+    ADD COLUMN IF NOT EXISTS bio                TEXT NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS sobrenome_publico  BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS cidade_publica     BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS desejadas_publicas BOOLEAN NOT NULL DEFAULT TRUE;
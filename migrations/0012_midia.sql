@@ -0,0 +1,26 @@
+-- Imagens enviadas pelos usuários: avatar (um por usuário) e portfólio de cada oferta.
+-- Os arquivos ficam no Storage configurado; o banco guarda apenas as chaves.
+
+ALTER TABLE public.users
+    ADD COLUMN IF NOT EXISTS avatar_chave           TEXT,
+    ADD COLUMN IF NOT EXISTS avatar_miniatura_chave TEXT;
+
+CREATE TABLE IF NOT EXISTS public.portfolio_imagem (
+    id                SERIAL PRIMARY KEY,
+    id_users          INTEGER NOT NULL,
+    id_sub_habilidade INTEGER NOT NULL,
+    chave             TEXT NOT NULL,
+    chave_miniatura   TEXT NOT NULL,
+    content_type      VARCHAR(50) NOT NULL,
+    tamanho_bytes     INTEGER NOT NULL,
+    largura           INTEGER NOT NULL,
+    altura            INTEGER NOT NULL,
+    created_at        TIMESTAMP NOT NULL DEFAULT now(),
+    -- Acompanha a oferta quando a sub-habilidade é mesclada em outra
+    FOREIGN KEY (id_users, id_sub_habilidade)
+        REFERENCES public.usuario_sub_habilidade (id_users, id_sub_habilidade)
+        ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS portfolio_imagem_oferta_idx
+    ON public.portfolio_imagem (id_users, id_sub_habilidade, id);
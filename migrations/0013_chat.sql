@@ -0,0 +1,26 @@
+-- Chat entre usuários com match. Cada par tem uma única conversa, guardada com
+-- o menor id em id_usuario1; as mensagens ficam mesmo se o match for desfeito,
+-- mas só voltam a ser acessíveis com um novo match.
+
+CREATE TABLE IF NOT EXISTS public.conversa (
+    id            SERIAL PRIMARY KEY,
+    id_usuario1   INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_usuario2   INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    created_at    TIMESTAMP NOT NULL DEFAULT now(),
+    -- Momento da última mensagem (ou da criação); ordena a lista de conversas
+    atualizada_em TIMESTAMP NOT NULL DEFAULT now(),
+    CHECK (id_usuario1 < id_usuario2),
+    UNIQUE (id_usuario1, id_usuario2)
+);
+
+CREATE INDEX IF NOT EXISTS conversa_id_usuario2_idx ON public.conversa (id_usuario2);
+
+CREATE TABLE IF NOT EXISTS public.mensagem (
+    id           BIGSERIAL PRIMARY KEY,
+    id_conversa  INTEGER NOT NULL REFERENCES public.conversa (id) ON DELETE CASCADE,
+    id_remetente INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    conteudo     TEXT NOT NULL,
+    created_at   TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS mensagem_conversa_idx ON public.mensagem (id_conversa, id DESC);
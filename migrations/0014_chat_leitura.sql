@@ -0,0 +1,9 @@
+-- Até qual mensagem cada participante leu a conversa. Sem linha, nada foi lido.
+
+CREATE TABLE IF NOT EXISTS public.leitura_conversa (
+    id_conversa    INTEGER NOT NULL REFERENCES public.conversa (id) ON DELETE CASCADE,
+    id_users       INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_ultima_lida BIGINT NOT NULL,
+    lida_em        TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (id_conversa, id_users)
+);
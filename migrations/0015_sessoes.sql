@@ -0,0 +1,44 @@
+-- Sessões de troca entre usuários com match. Quem propõe escolhe o que cada lado
+-- ensina, quando e onde; o outro aceita, recusa ou propõe outro horário.
+-- Transições: proposta → confirmada → concluida | cancelada | nao_compareceu,
+-- e proposta → recusada | cancelada.
+
+CREATE TABLE IF NOT EXISTS public.sessao_troca (
+    id                           SERIAL PRIMARY KEY,
+    id_proponente                INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_convidado                 INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    -- O que cada lado ensina; um dos dois pode ficar vazio (troca só de um lado)
+    id_sub_habilidade_proponente INTEGER REFERENCES public.sub_habilidade (id),
+    id_sub_habilidade_convidado  INTEGER REFERENCES public.sub_habilidade (id),
+    inicio                       TIMESTAMPTZ NOT NULL,
+    duracao_minutos              INTEGER NOT NULL,
+    modalidade                   VARCHAR(20) NOT NULL,
+    local                        TEXT,
+    link                         TEXT,
+    status                       VARCHAR(20) NOT NULL DEFAULT 'proposta',
+    -- Quem precisa responder à proposta atual
+    id_aguardando                INTEGER REFERENCES public.users (id) ON DELETE SET NULL,
+    concluida_proponente         BOOLEAN NOT NULL DEFAULT FALSE,
+    concluida_convidado          BOOLEAN NOT NULL DEFAULT FALSE,
+    id_ausente                   INTEGER REFERENCES public.users (id) ON DELETE SET NULL,
+    cancelada_por                INTEGER REFERENCES public.users (id) ON DELETE SET NULL,
+    motivo                       TEXT,
+    -- Incrementada a cada alteração (reagendamento, confirmação, cancelamento...)
+    sequencia                    INTEGER NOT NULL DEFAULT 0,
+    created_at                   TIMESTAMP NOT NULL DEFAULT now(),
+    atualizada_em                TIMESTAMP NOT NULL DEFAULT now(),
+    CONSTRAINT sessao_troca_participantes_check CHECK (id_proponente <> id_convidado),
+    CONSTRAINT sessao_troca_ensino_check
+        CHECK (id_sub_habilidade_proponente IS NOT NULL OR id_sub_habilidade_convidado IS NOT NULL),
+    CONSTRAINT sessao_troca_duracao_check CHECK (duracao_minutos BETWEEN 15 AND 480),
+    CONSTRAINT sessao_troca_modalidade_check CHECK (
+        (modalidade = 'presencial' AND local IS NOT NULL)
+        OR (modalidade = 'online' AND link IS NOT NULL)
+    ),
+    CONSTRAINT sessao_troca_status_check CHECK (
+        status IN ('proposta', 'confirmada', 'concluida', 'cancelada', 'recusada', 'nao_compareceu')
+    )
+);
+
+CREATE INDEX IF NOT EXISTS sessao_troca_proponente_idx ON public.sessao_troca (id_proponente, inicio);
+CREATE INDEX IF NOT EXISTS sessao_troca_convidado_idx ON public.sessao_troca (id_convidado, inicio);
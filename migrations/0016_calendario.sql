@@ -0,0 +1,18 @@
+-- Assinatura do calendário (.ics) de cada usuário. Só o hash do token da URL fica
+-- guardado; gerar um novo token invalida o anterior.
+
+CREATE TABLE IF NOT EXISTS public.calendario_assinatura (
+    id_users   INTEGER PRIMARY KEY REFERENCES public.users (id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+-- Sessões que chegaram a ser confirmadas entram no feed. Se forem canceladas ou
+-- voltarem a ser propostas depois disso, continuam nele para que os calendários
+-- atualizem o evento em vez de mantê-lo como estava.
+ALTER TABLE public.sessao_troca
+    ADD COLUMN IF NOT EXISTS confirmada_em TIMESTAMP;
+
+UPDATE public.sessao_troca
+SET confirmada_em = atualizada_em
+WHERE confirmada_em IS NULL AND status IN ('confirmada', 'concluida', 'nao_compareceu');
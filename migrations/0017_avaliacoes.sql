@@ -0,0 +1,34 @@
+-- Avaliações entre os participantes de uma sessão concluída. Cada um avalia o outro
+-- uma vez, com uma nota por sub-habilidade que o outro ensinou. A avaliação fica
+-- oculta até as duas serem enviadas ou o prazo terminar (visivel_em).
+
+ALTER TABLE public.sessao_troca
+    ADD COLUMN IF NOT EXISTS concluida_em TIMESTAMPTZ;
+
+UPDATE public.sessao_troca
+SET concluida_em = atualizada_em::timestamptz
+WHERE status = 'concluida' AND concluida_em IS NULL;
+
+CREATE TABLE IF NOT EXISTS public.avaliacao (
+    id           SERIAL PRIMARY KEY,
+    id_sessao    INTEGER NOT NULL REFERENCES public.sessao_troca (id) ON DELETE CASCADE,
+    id_avaliador INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_avaliado  INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    comentario   TEXT,
+    -- Prazo da sessão, antecipado para o envio da segunda avaliação
+    visivel_em   TIMESTAMPTZ NOT NULL,
+    created_at   TIMESTAMP NOT NULL DEFAULT now(),
+    CONSTRAINT avaliacao_sessao_avaliador_key UNIQUE (id_sessao, id_avaliador),
+    CONSTRAINT avaliacao_participantes_check CHECK (id_avaliador <> id_avaliado)
+);
+
+CREATE INDEX IF NOT EXISTS avaliacao_avaliado_idx ON public.avaliacao (id_avaliado, id DESC);
+
+CREATE TABLE IF NOT EXISTS public.avaliacao_nota (
+    id_avaliacao      INTEGER NOT NULL REFERENCES public.avaliacao (id) ON DELETE CASCADE,
+    id_sub_habilidade INTEGER NOT NULL REFERENCES public.sub_habilidade (id),
+    nota              SMALLINT NOT NULL CHECK (nota BETWEEN 1 AND 5),
+    PRIMARY KEY (id_avaliacao, id_sub_habilidade)
+);
+
+CREATE INDEX IF NOT EXISTS avaliacao_nota_sub_habilidade_idx ON public.avaliacao_nota (id_sub_habilidade);
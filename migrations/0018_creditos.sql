@@ -0,0 +1,74 @@
+-- Banco de horas: razão de partidas dobradas. Cada transação tem lançamentos que
+-- somam zero; o saldo de uma conta é a soma dos seus lançamentos. Valores positivos
+-- creditam a conta e negativos debitam. Lançamentos não podem ser alterados nem apagados.
+
+CREATE TABLE IF NOT EXISTS public.conta_credito (
+    id         SERIAL PRIMARY KEY,
+    id_users   INTEGER NOT NULL UNIQUE REFERENCES public.users (id),
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS public.transacao_credito (
+    id         BIGSERIAL PRIMARY KEY,
+    tipo       VARCHAR(30) NOT NULL,
+    id_sessao  INTEGER REFERENCES public.sessao_troca (id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    CONSTRAINT transacao_credito_tipo_check CHECK (tipo IN ('sessao'))
+);
+
+-- Uma sessão concluída gera uma única transação
+CREATE UNIQUE INDEX IF NOT EXISTS transacao_credito_sessao_key
+    ON public.transacao_credito (id_sessao, tipo) WHERE id_sessao IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS public.lancamento_credito (
+    id           BIGSERIAL PRIMARY KEY,
+    id_transacao BIGINT NOT NULL REFERENCES public.transacao_credito (id),
+    id_conta     INTEGER NOT NULL REFERENCES public.conta_credito (id),
+    valor        NUMERIC(14, 2) NOT NULL,
+    descricao    TEXT NOT NULL,
+    CONSTRAINT lancamento_credito_valor_check CHECK (valor <> 0)
+);
+
+CREATE INDEX IF NOT EXISTS lancamento_credito_conta_idx ON public.lancamento_credito (id_conta, id);
+CREATE INDEX IF NOT EXISTS lancamento_credito_transacao_idx ON public.lancamento_credito (id_transacao);
+
+CREATE OR REPLACE FUNCTION public.razao_imutavel()
+RETURNS trigger
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    RAISE EXCEPTION 'O razão de créditos não pode ser alterado (%)', TG_TABLE_NAME
+        USING ERRCODE = 'check_violation';
+END
+$$;
+
+DROP TRIGGER IF EXISTS lancamento_credito_imutavel ON public.lancamento_credito;
+CREATE TRIGGER lancamento_credito_imutavel
+    BEFORE UPDATE OR DELETE ON public.lancamento_credito
+    FOR EACH ROW EXECUTE FUNCTION public.razao_imutavel();
+
+-- A transação só perde a referência à sessão (ON DELETE SET NULL); nunca é apagada
+DROP TRIGGER IF EXISTS transacao_credito_imutavel ON public.transacao_credito;
+CREATE TRIGGER transacao_credito_imutavel
+    BEFORE DELETE ON public.transacao_credito
+    FOR EACH ROW EXECUTE FUNCTION public.razao_imutavel();
+
+-- Conferida no commit, depois de todos os lançamentos da transação
+CREATE OR REPLACE FUNCTION public.conferir_transacao_credito()
+RETURNS trigger
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    IF (SELECT SUM(valor) FROM public.lancamento_credito WHERE id_transacao = NEW.id_transacao) <> 0 THEN
+        RAISE EXCEPTION 'Transação de créditos % não fecha em zero', NEW.id_transacao
+            USING ERRCODE = 'check_violation';
+    END IF;
+    RETURN NULL;
+END
+$$;
+
+DROP TRIGGER IF EXISTS lancamento_credito_balanceado ON public.lancamento_credito;
+CREATE CONSTRAINT TRIGGER lancamento_credito_balanceado
+    AFTER INSERT ON public.lancamento_credito
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION public.conferir_transacao_credito();
@@ -0,0 +1,66 @@
+-- Custódia de créditos: ao confirmar uma sessão, o preço de cada aula sai da conta
+-- do aluno para a conta do sistema "custodia". Na conclusão vai para quem ensinou;
+-- no cancelamento dentro do prazo volta ao aluno. Uma disputa congela os créditos
+-- até a decisão de um moderador.
+
+ALTER TABLE public.conta_credito
+    ALTER COLUMN id_users DROP NOT NULL,
+    ADD COLUMN IF NOT EXISTS sistema VARCHAR(30) UNIQUE;
+
+ALTER TABLE public.conta_credito DROP CONSTRAINT IF EXISTS conta_credito_titular_check;
+ALTER TABLE public.conta_credito
+    ADD CONSTRAINT conta_credito_titular_check CHECK ((id_users IS NULL) <> (sistema IS NULL));
+
+INSERT INTO public.conta_credito (sistema) VALUES ('custodia') ON CONFLICT (sistema) DO NOTHING;
+
+ALTER TABLE public.transacao_credito DROP CONSTRAINT IF EXISTS transacao_credito_tipo_check;
+ALTER TABLE public.transacao_credito
+    ADD CONSTRAINT transacao_credito_tipo_check
+    CHECK (tipo IN ('sessao', 'retencao', 'liberacao', 'estorno'));
+
+-- Uma sessão reagendada pode ter várias retenções e estornos
+DROP INDEX IF EXISTS public.transacao_credito_sessao_key;
+CREATE INDEX IF NOT EXISTS transacao_credito_sessao_idx ON public.transacao_credito (id_sessao);
+
+-- Uma linha por aula reservada; o status acompanha o destino dos créditos
+CREATE TABLE IF NOT EXISTS public.custodia_credito (
+    id                     SERIAL PRIMARY KEY,
+    id_sessao              INTEGER NOT NULL REFERENCES public.sessao_troca (id),
+    id_aluno               INTEGER NOT NULL REFERENCES public.users (id),
+    id_professor           INTEGER NOT NULL REFERENCES public.users (id),
+    -- Nome da sub-habilidade ensinada, como estava na confirmação
+    aula                   TEXT NOT NULL,
+    valor                  NUMERIC(14, 2) NOT NULL,
+    status                 VARCHAR(20) NOT NULL DEFAULT 'retida',
+    id_transacao_retencao  BIGINT NOT NULL REFERENCES public.transacao_credito (id),
+    id_transacao_resolucao BIGINT REFERENCES public.transacao_credito (id),
+    created_at             TIMESTAMP NOT NULL DEFAULT now(),
+    resolvida_em           TIMESTAMP,
+    CONSTRAINT custodia_credito_valor_check CHECK (valor > 0),
+    CONSTRAINT custodia_credito_status_check
+        CHECK (status IN ('retida', 'congelada', 'liberada', 'estornada'))
+);
+
+CREATE INDEX IF NOT EXISTS custodia_credito_sessao_idx ON public.custodia_credito (id_sessao);
+CREATE INDEX IF NOT EXISTS custodia_credito_aluno_idx ON public.custodia_credito (id_aluno)
+    WHERE status IN ('retida', 'congelada');
+
+-- Aberta por um participante; congela a custódia da sessão até a decisão
+CREATE TABLE IF NOT EXISTS public.disputa_sessao (
+    id           SERIAL PRIMARY KEY,
+    id_sessao    INTEGER NOT NULL UNIQUE REFERENCES public.sessao_troca (id),
+    id_autor     INTEGER NOT NULL REFERENCES public.users (id),
+    motivo       TEXT NOT NULL,
+    status       VARCHAR(20) NOT NULL DEFAULT 'aberta',
+    -- liberar: créditos para quem ensinou; estornar: de volta aos alunos
+    decisao      VARCHAR(20),
+    observacao   TEXT,
+    id_moderador INTEGER REFERENCES public.users (id),
+    created_at   TIMESTAMP NOT NULL DEFAULT now(),
+    resolvida_em TIMESTAMP,
+    CONSTRAINT disputa_sessao_status_check CHECK (status IN ('aberta', 'resolvida')),
+    CONSTRAINT disputa_sessao_decisao_check CHECK (decisao IN ('liberar', 'estornar')),
+    CONSTRAINT disputa_sessao_resolvida_check CHECK ((status = 'resolvida') = (decisao IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS disputa_sessao_status_idx ON public.disputa_sessao (status, id);
@@ -0,0 +1,50 @@
+-- Bloqueios entre usuários e denúncias para a moderação. Um bloqueio vale nos dois
+-- sentidos: nenhum dos dois vê o outro em likes, matches, busca ou chat.
+
+CREATE TABLE IF NOT EXISTS public.bloqueio (
+    id_bloqueador INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    id_bloqueado  INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    created_at    TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (id_bloqueador, id_bloqueado),
+    CONSTRAINT bloqueio_par_check CHECK (id_bloqueador <> id_bloqueado)
+);
+
+-- As consultas procuram o par nos dois sentidos
+CREATE INDEX IF NOT EXISTS bloqueio_bloqueado_idx ON public.bloqueio (id_bloqueado, id_bloqueador);
+
+CREATE TABLE IF NOT EXISTS public.denuncia (
+    id            SERIAL PRIMARY KEY,
+    id_autor      INTEGER REFERENCES public.users (id) ON DELETE SET NULL,
+    id_denunciado INTEGER NOT NULL REFERENCES public.users (id) ON DELETE CASCADE,
+    categoria     VARCHAR(30) NOT NULL,
+    descricao     TEXT NOT NULL,
+    status        VARCHAR(20) NOT NULL DEFAULT 'pendente',
+    observacao    TEXT,
+    id_moderador  INTEGER REFERENCES public.users (id) ON DELETE SET NULL,
+    created_at    TIMESTAMP NOT NULL DEFAULT now(),
+    decidida_em   TIMESTAMP,
+    CONSTRAINT denuncia_categoria_check
+        CHECK (categoria IN ('assedio', 'spam', 'perfil_falso', 'conteudo_improprio', 'golpe', 'ausencia', 'outro')),
+    CONSTRAINT denuncia_status_check
+        CHECK (status IN ('pendente', 'procedente', 'improcedente')),
+    CONSTRAINT denuncia_par_check CHECK (id_autor <> id_denunciado)
+);
+
+-- Uma denúncia pendente por par; as seguintes esperam a decisão da anterior
+CREATE UNIQUE INDEX IF NOT EXISTS denuncia_pendente_key
+    ON public.denuncia (id_autor, id_denunciado)
+    WHERE status = 'pendente';
+
+CREATE INDEX IF NOT EXISTS denuncia_status_idx ON public.denuncia (status, id);
+
+CREATE INDEX IF NOT EXISTS denuncia_denunciado_idx ON public.denuncia (id_denunciado);
+
+-- Mensagens do chat anexadas como evidência, copiadas no momento da denúncia
+CREATE TABLE IF NOT EXISTS public.denuncia_evidencia (
+    id_denuncia  INTEGER NOT NULL REFERENCES public.denuncia (id) ON DELETE CASCADE,
+    id_mensagem  BIGINT NOT NULL,
+    id_remetente INTEGER REFERENCES public.users (id) ON DELETE SET NULL,
+    conteudo     TEXT NOT NULL,
+    enviada_em   TIMESTAMP NOT NULL,
+    PRIMARY KEY (id_denuncia, id_mensagem)
+);
@@ -0,0 +1,8 @@
+-- O valor das ofertas é o preço em créditos de uma hora de aula; com ponto flutuante
+-- o preço cobrado na sessão podia diferir do exibido. Passa a ter duas casas exatas.
+
+ALTER TABLE public.usuario_sub_habilidade
+    ALTER COLUMN valor TYPE NUMERIC(14, 2) USING round(valor::numeric, 2);
+
+ALTER TABLE public.sugestao_sub_habilidade
+    ALTER COLUMN valor_oferta TYPE NUMERIC(14, 2) USING round(valor_oferta::numeric, 2);
@@ -0,0 +1,60 @@
+-- Sessões confirmadas antes da custódia existir não têm créditos reservados. Reserva
+-- agora o preço de cada aula, como na confirmação, para que a conclusão só transfira o
+-- que está em custódia. O limite negativo não é conferido: a sessão já estava combinada.
+
+DO $$
+DECLARE
+    aula           RECORD;
+    sessao_atual   INTEGER;
+    id_transacao   BIGINT;
+    conta_aluno    INTEGER;
+    conta_custodia INTEGER;
+    descricao      TEXT;
+BEGIN
+    SELECT id INTO conta_custodia FROM public.conta_credito WHERE sistema = 'custodia';
+
+    FOR aula IN
+        SELECT
+            s.id AS id_sessao,
+            lado.id_professor,
+            lado.id_aluno,
+            sh.nome,
+            round(o.valor * s.duracao_minutos / 60, 2) AS preco
+        FROM public.sessao_troca s
+        CROSS JOIN LATERAL (VALUES
+            (s.id_proponente, s.id_convidado, s.id_sub_habilidade_proponente),
+            (s.id_convidado, s.id_proponente, s.id_sub_habilidade_convidado)
+        ) AS lado (id_professor, id_aluno, id_sub_habilidade)
+        JOIN public.usuario_sub_habilidade o
+          ON o.id_users = lado.id_professor AND o.id_sub_habilidade = lado.id_sub_habilidade
+        JOIN public.sub_habilidade sh ON sh.id = lado.id_sub_habilidade
+        WHERE s.status = 'confirmada'
+          AND NOT EXISTS (SELECT 1 FROM public.transacao_credito t WHERE t.id_sessao = s.id)
+          AND round(o.valor * s.duracao_minutos / 60, 2) > 0
+        ORDER BY s.id, lado.id_professor
+    LOOP
+        -- Uma transação de retenção por sessão, como em custodia::reter
+        IF sessao_atual IS DISTINCT FROM aula.id_sessao THEN
+            INSERT INTO public.transacao_credito (tipo, id_sessao)
+            VALUES ('retencao', aula.id_sessao)
+            RETURNING id INTO id_transacao;
+            sessao_atual := aula.id_sessao;
+        END IF;
+
+        INSERT INTO public.conta_credito (id_users) VALUES (aula.id_aluno)
+        ON CONFLICT (id_users) DO NOTHING;
+        SELECT id INTO conta_aluno FROM public.conta_credito WHERE id_users = aula.id_aluno;
+
+        descricao := format('Aula de %s reservada para a sessão %s', aula.nome, aula.id_sessao);
+        INSERT INTO public.lancamento_credito (id_transacao, id_conta, valor, descricao)
+        VALUES
+            (id_transacao, conta_aluno, -aula.preco, descricao),
+            (id_transacao, conta_custodia, aula.preco, descricao);
+
+        INSERT INTO public.custodia_credito
+            (id_sessao, id_aluno, id_professor, aula, valor, id_transacao_retencao)
+        VALUES
+            (aula.id_sessao, aula.id_aluno, aula.id_professor, aula.nome, aula.preco, id_transacao);
+    END LOOP;
+END
+$$;
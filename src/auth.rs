@@ -0,0 +1,473 @@
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::Utc;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::future::{ready, Ready};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, ToSchema};
+use validator::Validate;
+
+use crate::config::AuthConfig;
+use crate::error::ApiError;
+use crate::validacao::{self, JsonValidado};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TipoToken {
+    Access,
+    Refresh,
+}
+
+// A ordem das variantes define a hierarquia: admin pode tudo que moderator pode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: i32,
+    role: Role,
+    iat: i64,
+    exp: i64,
+    typ: TipoToken,
+}
+
+// Chaves e validade dos tokens, compartilhadas entre os workers via web::Data
+pub struct Jwt {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    access_ttl: i64,
+    refresh_ttl: i64,
+}
+
+impl Jwt {
+    pub fn new(config: &AuthConfig) -> Self {
+        Jwt {
+            encoding: EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+            decoding: DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+            access_ttl: config.access_token_ttl_secs as i64,
+            refresh_ttl: config.refresh_token_ttl_secs as i64,
+        }
+    }
+
+    fn assinar(&self, id_users: i32, role: Role, typ: TipoToken) -> Result<String, ApiError> {
+        let agora = Utc::now().timestamp();
+        let ttl = match typ {
+            TipoToken::Access => self.access_ttl,
+            TipoToken::Refresh => self.refresh_ttl,
+        };
+        let claims = Claims {
+            sub: id_users,
+            role,
+            iat: agora,
+            exp: agora + ttl,
+            typ,
+        };
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding).map_err(|e| {
+            log::error!("Erro ao assinar token: {:?}", e);
+            ApiError::Internal
+        })
+    }
+
+    fn emitir(&self, id_users: i32, role: Role) -> Result<Tokens, ApiError> {
+        Ok(Tokens {
+            id_users,
+            role,
+            access_token: self.assinar(id_users, role, TipoToken::Access)?,
+            refresh_token: self.assinar(id_users, role, TipoToken::Refresh)?,
+            token_type: "Bearer",
+            expires_in: self.access_ttl,
+        })
+    }
+
+    fn verificar(&self, token: &str, esperado: TipoToken) -> Result<Claims, ApiError> {
+        let dados = decode::<Claims>(token, &self.decoding, &Validation::new(Algorithm::HS256))
+            .map_err(|_| ApiError::Unauthorized("Token inválido ou expirado".to_string()))?;
+        if dados.claims.typ != esperado {
+            return Err(ApiError::Unauthorized("Tipo de token inválido".to_string()));
+        }
+        Ok(dados.claims)
+    }
+
+    // Valida um access token recebido fora do header (ex.: primeira mensagem do WebSocket)
+    pub fn autenticar(&self, token: &str) -> Result<AuthUser, ApiError> {
+        let claims = self.verificar(token.trim(), TipoToken::Access)?;
+        Ok(AuthUser {
+            id: claims.sub,
+            role: claims.role,
+        })
+    }
+}
+
+// Usuário autenticado, extraído do header `Authorization: Bearer <access token>`
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub id: i32,
+    pub role: Role,
+}
+
+impl AuthUser {
+    pub fn exigir_papel(&self, minimo: Role) -> Result<(), ApiError> {
+        if self.role < minimo {
+            return Err(ApiError::Forbidden(format!(
+                "Operação restrita ao papel {} ou superior",
+                minimo.as_str()
+            )));
+        }
+        Ok(())
+    }
+
+    // Recusa operações sobre dados de outro usuário
+    pub fn garantir_dono(&self, id_users: i32) -> Result<(), ApiError> {
+        if self.id != id_users {
+            return Err(ApiError::Forbidden(
+                "Operação permitida apenas sobre os próprios dados".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Para operações sobre um par de usuários (matches, histórico)
+    pub fn garantir_participante(&self, id1: i32, id2: i32) -> Result<(), ApiError> {
+        if self.id != id1 && self.id != id2 {
+            return Err(ApiError::Forbidden(
+                "Operação permitida apenas aos participantes".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(autenticar(req))
+    }
+}
+
+pub(crate) fn autenticar(req: &HttpRequest) -> Result<AuthUser, ApiError> {
+    let jwt = req.app_data::<web::Data<Jwt>>().ok_or_else(|| {
+        log::error!("Jwt não registrado em app_data");
+        ApiError::Internal
+    })?;
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::Unauthorized("Token de acesso ausente".to_string()))?;
+    jwt.autenticar(token)
+}
+
+// Extratores de guarda: a rota só executa se o token tiver o papel mínimo exigido
+pub struct Moderador(pub AuthUser);
+
+impl FromRequest for Moderador {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(autenticar(req).and_then(|auth| {
+            auth.exigir_papel(Role::Moderator)?;
+            Ok(Moderador(auth))
+        }))
+    }
+}
+
+pub struct Admin(pub AuthUser);
+
+impl FromRequest for Admin {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(autenticar(req).and_then(|auth| {
+            auth.exigir_papel(Role::Admin)?;
+            Ok(Admin(auth))
+        }))
+    }
+}
+
+// Registra o esquema de autenticação usado por `security(("bearer_auth" = []))`
+pub struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_auth",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+        }
+    }
+}
+
+async fn gerar_hash(senha: String) -> Result<String, ApiError> {
+    // Argon2 é caro de propósito; roda fora do executor assíncrono
+    web::block(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(senha.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await
+    .map_err(|_| ApiError::Internal)?
+    .map_err(|e| {
+        log::error!("Erro ao gerar hash da senha: {:?}", e);
+        ApiError::Internal
+    })
+}
+
+async fn senha_confere(senha: String, hash: String) -> Result<bool, ApiError> {
+    web::block(move || {
+        PasswordHash::new(&hash)
+            .map(|hash| Argon2::default().verify_password(senha.as_bytes(), &hash).is_ok())
+            .unwrap_or(false)
+    })
+    .await
+    .map_err(|_| ApiError::Internal)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Tokens {
+    id_users: i32,
+    role: Role,
+    access_token: String,
+    refresh_token: String,
+    token_type: &'static str,
+    // Validade do access token em segundos
+    expires_in: i64,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct RegistroRequest {
+    #[validate(email(message = "E-mail inválido"))]
+    email: String,
+    #[validate(length(min = 8, max = 128, message = "A senha deve ter entre 8 e 128 caracteres"))]
+    senha: String,
+    #[validate(
+        length(max = 100, message = "O nome deve ter no máximo 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    first_name: String,
+    #[validate(
+        length(max = 100, message = "O sobrenome deve ter no máximo 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    last_name: String,
+    #[validate(length(max = 100, message = "A cidade deve ter no máximo 100 caracteres"))]
+    city: String,
+    #[validate(length(max = 1000, message = "A bio deve ter no máximo 1000 caracteres"))]
+    #[serde(default)]
+    bio: String,
+}
+
+// POST: Cria um usuário com senha e já devolve os tokens
+#[utoipa::path(
+    post,
+    path = "/auth/registrar",
+    request_body = RegistroRequest,
+    responses(
+        (status = 201, description = "Usuário registrado", body = Tokens),
+        (status = 409, description = "E-mail já cadastrado", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao registrar usuário", body = ProblemDetails)
+    )
+)]
+pub async fn registrar(
+    pool: web::Data<sqlx::PgPool>,
+    jwt: web::Data<Jwt>,
+    dados: JsonValidado<RegistroRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let dados = dados.into_inner();
+    let hash = gerar_hash(dados.senha).await?;
+
+    let query = r#"
+        INSERT INTO public.users (first_name, last_name, city, email, password_hash, bio)
+        VALUES ($1, $2, $3, lower($4), $5, $6)
+        RETURNING id
+    "#;
+
+    let id_users = sqlx::query_scalar::<_, i32>(query)
+        .bind(dados.first_name.trim())
+        .bind(dados.last_name.trim())
+        .bind(dados.city.trim())
+        .bind(dados.email.trim())
+        .bind(hash)
+        .bind(dados.bio.trim())
+        .fetch_one(pool.get_ref())
+        .await
+        .map_err(|e| match ApiError::from(e) {
+            ApiError::Conflict(_) => ApiError::Conflict("E-mail já cadastrado".to_string()),
+            outro => outro,
+        })?;
+
+    Ok(HttpResponse::Created()
+        .insert_header(sem_cache())
+        .json(jwt.emitir(id_users, Role::User)?))
+}
+
+// Respostas com credenciais não ficam em cache nem no armazenamento de idempotência
+pub fn sem_cache() -> header::CacheControl {
+    header::CacheControl(vec![header::CacheDirective::NoStore])
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct LoginRequest {
+    #[validate(email(message = "E-mail inválido"))]
+    email: String,
+    #[validate(length(min = 1, message = "A senha é obrigatória"))]
+    senha: String,
+}
+
+// POST: Troca e-mail e senha por um par de tokens
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Login realizado", body = Tokens),
+        (status = 401, description = "Credenciais inválidas", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao realizar login", body = ProblemDetails)
+    )
+)]
+pub async fn login(
+    pool: web::Data<sqlx::PgPool>,
+    jwt: web::Data<Jwt>,
+    dados: JsonValidado<LoginRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let LoginRequest { email, senha } = dados.into_inner();
+
+    let query = r#"
+        SELECT id, password_hash, role
+        FROM public.users
+        WHERE lower(email) = lower($1) AND password_hash IS NOT NULL
+    "#;
+
+    let usuario = sqlx::query_as::<_, (i32, String, Role)>(query)
+        .bind(email.trim())
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    let credenciais_invalidas = || ApiError::Unauthorized("Credenciais inválidas".to_string());
+    let (id_users, hash, role) = usuario.ok_or_else(credenciais_invalidas)?;
+    if !senha_confere(senha, hash).await? {
+        return Err(credenciais_invalidas());
+    }
+
+    Ok(HttpResponse::Ok()
+        .insert_header(sem_cache())
+        .json(jwt.emitir(id_users, role)?))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    refresh_token: String,
+}
+
+// POST: Emite novos tokens a partir de um refresh token válido
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "Tokens renovados", body = Tokens),
+        (status = 401, description = "Refresh token inválido ou expirado", body = ProblemDetails),
+        (status = 500, description = "Erro ao renovar tokens", body = ProblemDetails)
+    )
+)]
+pub async fn renovar_token(
+    pool: web::Data<sqlx::PgPool>,
+    jwt: web::Data<Jwt>,
+    dados: web::Json<RefreshRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let claims = jwt.verificar(&dados.refresh_token, TipoToken::Refresh)?;
+
+    // O papel é relido do banco: o usuário pode ter sido removido ou mudado de papel
+    let role = sqlx::query_scalar::<_, Role>("SELECT role FROM public.users WHERE id = $1")
+        .bind(claims.sub)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::Unauthorized("Usuário não encontrado".to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(sem_cache())
+        .json(jwt.emitir(claims.sub, role)?))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AlterarPapelRequest {
+    role: Role,
+}
+
+// PUT: Altera o papel de um usuário (somente admin)
+#[utoipa::path(
+    put,
+    path = "/admin/usuarios/{id}/role",
+    params(
+        ("id" = i32, Path, description = "ID do usuário")
+    ),
+    request_body = AlterarPapelRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Papel alterado. Requer papel admin."),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel admin", body = ProblemDetails),
+        (status = 404, description = "Usuário não encontrado", body = ProblemDetails),
+        (status = 500, description = "Erro ao alterar papel", body = ProblemDetails)
+    )
+)]
+pub async fn alterar_papel(
+    pool: web::Data<sqlx::PgPool>,
+    Admin(admin): Admin,
+    path: web::Path<i32>,
+    dados: web::Json<AlterarPapelRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+
+    // Evita que o último acesso administrativo seja removido por engano
+    if id_users == admin.id && dados.role != Role::Admin {
+        return Err(ApiError::Conflict(
+            "Um admin não pode remover o próprio papel".to_string(),
+        ));
+    }
+
+    let result = sqlx::query("UPDATE public.users SET role = $1 WHERE id = $2")
+        .bind(dados.role)
+        .bind(id_users)
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound("Usuário não encontrado".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json("Papel alterado com sucesso"))
+}
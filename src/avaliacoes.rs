@@ -0,0 +1,455 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::auth::AuthUser;
+use crate::error::{ApiError, FieldViolation};
+use crate::paginacao::{self, Pagina, PaginaQuery};
+use crate::sessoes::{self, Sessao, StatusSessao};
+use crate::validacao::{self, JsonValidado};
+
+// Dias, contados da conclusão da sessão, para enviar a avaliação. Terminado o prazo,
+// as avaliações enviadas ficam visíveis mesmo que o outro participante não tenha avaliado
+const PRAZO_AVALIACAO_DIAS: i64 = 14;
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Nota {
+    #[serde(skip)]
+    id_avaliacao: i32,
+    id_sub_habilidade: i32,
+    nome_sub_habilidade: String,
+    nota: i16,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Avaliacao {
+    id: i32,
+    id_sessao: i32,
+    id_avaliador: i32,
+    nome_avaliador: String,
+    id_avaliado: i32,
+    comentario: Option<String>,
+    // Uma por sub-habilidade ensinada pelo avaliado na sessão
+    #[sqlx(skip)]
+    notas: Vec<Nota>,
+    // A partir de quando o avaliado e o perfil público enxergam a avaliação
+    visivel_em: DateTime<Utc>,
+    created_at: NaiveDateTime,
+}
+
+const SELECT_AVALIACAO: &str = r#"
+    SELECT
+        a.id, a.id_sessao, a.id_avaliador,
+        CASE
+            WHEN u.sobrenome_publico THEN CONCAT(u.first_name, ' ', u.last_name)
+            ELSE u.first_name
+        END AS nome_avaliador,
+        a.id_avaliado, a.comentario, a.visivel_em, a.created_at
+    FROM public.avaliacao a
+    JOIN public.users u ON u.id = a.id_avaliador
+"#;
+
+// As duas avaliações de uma sessão, na visão de um dos participantes
+#[derive(Serialize, ToSchema)]
+pub struct AvaliacoesSessao {
+    // Até quando a sessão pode ser avaliada; nulo enquanto ela não for concluída
+    prazo: Option<DateTime<Utc>>,
+    minha: Option<Avaliacao>,
+    // Fica nula até as duas avaliações serem enviadas ou o prazo terminar
+    do_outro: Option<Avaliacao>,
+    outro_avaliou: bool,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ResumoSubHabilidade {
+    id_sub_habilidade: i32,
+    nome_sub_habilidade: String,
+    media: f64,
+    total: i64,
+}
+
+// Considera apenas avaliações já visíveis
+#[derive(Serialize, ToSchema)]
+pub struct ResumoAvaliacoes {
+    // Média das notas recebidas, com duas casas; nula sem notas
+    media: Option<f64>,
+    // Quantidade de avaliações recebidas
+    total: i64,
+    por_sub_habilidade: Vec<ResumoSubHabilidade>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct NotaRequest {
+    #[validate(range(min = 1, message = "ID de sub-habilidade inválido"))]
+    id_sub_habilidade: i32,
+    #[validate(range(min = 1, max = 5, message = "A nota deve estar entre 1 e 5"))]
+    nota: i16,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validar_avaliacao"))]
+pub struct AvaliacaoRequest {
+    // Uma nota para cada sub-habilidade que o outro participante ensinou
+    #[serde(default)]
+    #[validate(nested)]
+    notas: Vec<NotaRequest>,
+    #[validate(
+        length(min = 1, max = 1000, message = "O comentário deve ter entre 1 e 1000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    comentario: Option<String>,
+}
+
+fn validar_avaliacao(dados: &AvaliacaoRequest) -> Result<(), ValidationError> {
+    let mut ids: Vec<i32> = dados.notas.iter().map(|n| n.id_sub_habilidade).collect();
+    ids.sort_unstable();
+    if ids.windows(2).any(|par| par[0] == par[1]) {
+        return Err(validacao::erro_no_campo(
+            "notas",
+            "duplicate",
+            "Informe uma única nota por sub-habilidade",
+        ));
+    }
+    Ok(())
+}
+
+// Sub-habilidades que o avaliado ensinou na sessão
+fn ensinadas_por(sessao: &Sessao, id_avaliado: i32) -> Vec<i32> {
+    let ensinada = if id_avaliado == sessao.id_proponente {
+        sessao.id_sub_habilidade_proponente
+    } else {
+        sessao.id_sub_habilidade_convidado
+    };
+    ensinada.into_iter().collect()
+}
+
+// As notas precisam cobrir exatamente o que o avaliado ensinou; quem não ensinou nada
+// recebe só o comentário
+fn conferir_notas(dados: &AvaliacaoRequest, ensinadas: &[i32]) -> Result<(), ApiError> {
+    let mut violacoes = Vec::new();
+    for (indice, nota) in dados.notas.iter().enumerate() {
+        if !ensinadas.contains(&nota.id_sub_habilidade) {
+            violacoes.push(FieldViolation {
+                field: format!("notas[{}].id_sub_habilidade", indice),
+                code: "not_taught".to_string(),
+                message: "A sub-habilidade não foi ensinada pelo avaliado nesta sessão".to_string(),
+            });
+        }
+    }
+    if ensinadas
+        .iter()
+        .any(|id| !dados.notas.iter().any(|n| n.id_sub_habilidade == *id))
+    {
+        violacoes.push(FieldViolation {
+            field: "notas".to_string(),
+            code: "required".to_string(),
+            message: "Informe a nota de cada sub-habilidade ensinada pelo avaliado".to_string(),
+        });
+    }
+    if ensinadas.is_empty() && dados.comentario.is_none() {
+        violacoes.push(FieldViolation {
+            field: "comentario".to_string(),
+            code: "required".to_string(),
+            message: "O avaliado não ensinou nesta sessão; deixe um comentário".to_string(),
+        });
+    }
+    if violacoes.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation(violacoes))
+    }
+}
+
+fn prazo_avaliacao(sessao: &Sessao) -> Option<DateTime<Utc>> {
+    sessao
+        .concluida_em
+        .map(|concluida_em| concluida_em + Duration::days(PRAZO_AVALIACAO_DIAS))
+}
+
+// Preenche as notas de cada avaliação
+async fn com_notas(
+    executor: impl sqlx::PgExecutor<'_>,
+    mut avaliacoes: Vec<Avaliacao>,
+) -> Result<Vec<Avaliacao>, ApiError> {
+    if avaliacoes.is_empty() {
+        return Ok(avaliacoes);
+    }
+    let ids: Vec<i32> = avaliacoes.iter().map(|a| a.id).collect();
+
+    let query = r#"
+        SELECT n.id_avaliacao, n.id_sub_habilidade, s.nome AS nome_sub_habilidade, n.nota
+        FROM public.avaliacao_nota n
+        JOIN public.sub_habilidade s ON s.id = n.id_sub_habilidade
+        WHERE n.id_avaliacao = ANY($1)
+        ORDER BY s.nome
+    "#;
+
+    let notas = sqlx::query_as::<_, Nota>(query)
+        .bind(&ids)
+        .fetch_all(executor)
+        .await?;
+
+    let mut por_avaliacao: HashMap<i32, Vec<Nota>> = HashMap::new();
+    for nota in notas {
+        por_avaliacao.entry(nota.id_avaliacao).or_default().push(nota);
+    }
+    for avaliacao in &mut avaliacoes {
+        avaliacao.notas = por_avaliacao.remove(&avaliacao.id).unwrap_or_default();
+    }
+    Ok(avaliacoes)
+}
+
+pub async fn resumo(pool: &PgPool, id_users: i32) -> Result<ResumoAvaliacoes, ApiError> {
+    let query = r#"
+        SELECT
+            (SELECT COUNT(*) FROM public.avaliacao a
+             WHERE a.id_avaliado = $1 AND a.visivel_em <= now()) AS total,
+            (SELECT ROUND(AVG(n.nota), 2)::float8
+             FROM public.avaliacao a
+             JOIN public.avaliacao_nota n ON n.id_avaliacao = a.id
+             WHERE a.id_avaliado = $1 AND a.visivel_em <= now()) AS media
+    "#;
+
+    let (total, media) = sqlx::query_as::<_, (i64, Option<f64>)>(query)
+        .bind(id_users)
+        .fetch_one(pool)
+        .await?;
+
+    let query = r#"
+        SELECT
+            n.id_sub_habilidade,
+            s.nome AS nome_sub_habilidade,
+            ROUND(AVG(n.nota), 2)::float8 AS media,
+            COUNT(*) AS total
+        FROM public.avaliacao a
+        JOIN public.avaliacao_nota n ON n.id_avaliacao = a.id
+        JOIN public.sub_habilidade s ON s.id = n.id_sub_habilidade
+        WHERE a.id_avaliado = $1 AND a.visivel_em <= now()
+        GROUP BY n.id_sub_habilidade, s.nome
+        ORDER BY s.nome
+    "#;
+
+    let por_sub_habilidade = sqlx::query_as::<_, ResumoSubHabilidade>(query)
+        .bind(id_users)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(ResumoAvaliacoes {
+        media,
+        total,
+        por_sub_habilidade,
+    })
+}
+
+// POST: Avalia o outro participante de uma sessão concluída
+#[utoipa::path(
+    post,
+    path = "/sessoes/{id}/avaliacoes",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    request_body = AvaliacaoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Avaliação registrada; fica oculta para o avaliado até a revelação", body = Avaliacao),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sessão não concluída, prazo encerrado ou sessão já avaliada pelo usuário", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos ou notas que não correspondem ao que o avaliado ensinou", body = ProblemDetails),
+        (status = 500, description = "Erro ao registrar avaliação", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn avaliar_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    dados: JsonValidado<AvaliacaoRequest>,
+) -> Result<HttpResponse, ApiError> {
+    // A trava da sessão serializa as duas avaliações, para a revelação não se perder
+    let mut tx = pool.begin().await?;
+    let sessao = sessoes::travar_sessao(&mut tx, path.into_inner(), &auth).await?;
+    sessoes::exigir_status(&sessao, &[StatusSessao::Concluida])?;
+
+    let prazo = prazo_avaliacao(&sessao).ok_or(ApiError::Internal)?;
+    if Utc::now() >= prazo {
+        return Err(ApiError::Conflict(
+            "O prazo para avaliar esta sessão terminou".to_string(),
+        ));
+    }
+
+    let id_avaliado = sessao.outro(auth.id);
+    conferir_notas(&dados, &ensinadas_por(&sessao, id_avaliado))?;
+
+    let avaliacoes = sqlx::query_scalar::<_, i32>(
+        "SELECT id_avaliador FROM public.avaliacao WHERE id_sessao = $1",
+    )
+    .bind(sessao.id)
+    .fetch_all(&mut *tx)
+    .await?;
+    if avaliacoes.contains(&auth.id) {
+        return Err(ApiError::Conflict("Você já avaliou esta sessão".to_string()));
+    }
+    let outro_avaliou = avaliacoes.contains(&id_avaliado);
+
+    let query = r#"
+        INSERT INTO public.avaliacao (id_sessao, id_avaliador, id_avaliado, comentario, visivel_em)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+    "#;
+
+    let id = sqlx::query_scalar::<_, i32>(query)
+        .bind(sessao.id)
+        .bind(auth.id)
+        .bind(id_avaliado)
+        .bind(dados.comentario.as_deref().map(str::trim))
+        .bind(prazo)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    for nota in &dados.notas {
+        sqlx::query(
+            "INSERT INTO public.avaliacao_nota (id_avaliacao, id_sub_habilidade, nota) VALUES ($1, $2, $3)",
+        )
+        .bind(id)
+        .bind(nota.id_sub_habilidade)
+        .bind(nota.nota)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // Com as duas avaliações enviadas, ambas são reveladas
+    if outro_avaliou {
+        sqlx::query("UPDATE public.avaliacao SET visivel_em = now() WHERE id_sessao = $1")
+            .bind(sessao.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let query = format!("{} WHERE a.id = $1", SELECT_AVALIACAO);
+    let avaliacao = sqlx::query_as::<_, Avaliacao>(&query)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let avaliacao = com_notas(&mut *tx, vec![avaliacao]).await?;
+
+    tx.commit().await?;
+    Ok(HttpResponse::Created().json(&avaliacao[0]))
+}
+
+// GET: Avaliações de uma sessão; a do outro participante só aparece depois da revelação
+#[utoipa::path(
+    get,
+    path = "/sessoes/{id}/avaliacoes",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Avaliação do usuário e, se revelada, a do outro participante", body = AvaliacoesSessao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar avaliações", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_avaliacoes_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_sessao = path.into_inner();
+
+    let (id_proponente, id_convidado, concluida_em) =
+        sqlx::query_as::<_, (i32, i32, Option<DateTime<Utc>>)>(
+            "SELECT id_proponente, id_convidado, concluida_em FROM public.sessao_troca WHERE id = $1",
+        )
+        .bind(id_sessao)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Sessão não encontrada".to_string()))?;
+
+    auth.garantir_participante(id_proponente, id_convidado)?;
+
+    let query = format!("{} WHERE a.id_sessao = $1", SELECT_AVALIACAO);
+    let avaliacoes = sqlx::query_as::<_, Avaliacao>(&query)
+        .bind(id_sessao)
+        .fetch_all(pool.get_ref())
+        .await?;
+    let avaliacoes = com_notas(pool.get_ref(), avaliacoes).await?;
+
+    let mut resposta = AvaliacoesSessao {
+        prazo: concluida_em.map(|c| c + Duration::days(PRAZO_AVALIACAO_DIAS)),
+        minha: None,
+        do_outro: None,
+        outro_avaliou: false,
+    };
+    for avaliacao in avaliacoes {
+        if avaliacao.id_avaliador == auth.id {
+            resposta.minha = Some(avaliacao);
+        } else {
+            resposta.outro_avaliou = true;
+            if avaliacao.visivel_em <= Utc::now() {
+                resposta.do_outro = Some(avaliacao);
+            }
+        }
+    }
+    Ok(HttpResponse::Ok().json(resposta))
+}
+
+// GET: Avaliações recebidas por um usuário, das mais recentes para as mais antigas
+#[utoipa::path(
+    get,
+    path = "/usuarios/{id}/avaliacoes",
+    params(
+        ("id" = i32, Path, description = "ID do usuário avaliado"),
+        PaginaQuery
+    ),
+    responses(
+        (status = 200, description = "Avaliações já reveladas", body = PaginaAvaliacoes),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar avaliações", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn listar_avaliacoes_usuario(
+    pool: web::Data<PgPool>,
+    path: web::Path<i32>,
+    params: web::Query<PaginaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    validacao::validar(&*params)?;
+    let after = paginacao::decodificar::<i32>(params.after.as_deref())?;
+
+    let query = format!(
+        r#"
+        {}
+        WHERE a.id_avaliado = $1 AND a.visivel_em <= now()
+          AND ($2::int4 IS NULL OR a.id < $2)
+        ORDER BY a.id DESC
+        LIMIT $3
+        "#,
+        SELECT_AVALIACAO
+    );
+
+    let avaliacoes = sqlx::query_as::<_, Avaliacao>(&query)
+        .bind(path.into_inner())
+        .bind(after)
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(avaliacoes, params.limit, |a| a.id)?;
+    let pagina = Pagina {
+        items: com_notas(pool.get_ref(), pagina.items).await?,
+        next_cursor: pagina.next_cursor,
+    };
+    Ok(HttpResponse::Ok().json(pagina))
+}
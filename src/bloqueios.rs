@@ -0,0 +1,334 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::auth::AuthUser;
+use crate::custodia;
+use crate::error::{ApiError, FieldViolation};
+use crate::paginacao::{self, Pagina, PaginaQuery};
+use crate::validacao::{self, JsonValidado, Referencias};
+
+// Um lado do par na condição de bloqueio
+#[derive(Clone, Copy)]
+enum Lado<'a> {
+    // Expressão SQL da consulta (coluna ou parâmetro já numerado)
+    Coluna(&'a str),
+    // ID passado como bind
+    Id(i32),
+}
+
+impl Lado<'_> {
+    fn push(self, qb: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            Lado::Coluna(coluna) => qb.push(coluna),
+            Lado::Id(id) => qb.push_bind(id),
+        };
+    }
+}
+
+// Condição SQL: nenhum dos dois usuários bloqueou o outro
+fn condicao(qb: &mut QueryBuilder<'_, Postgres>, a: Lado, b: Lado) {
+    qb.push("NOT EXISTS (SELECT 1 FROM public.bloqueio bl WHERE (bl.id_bloqueador = ");
+    a.push(qb);
+    qb.push(" AND bl.id_bloqueado = ");
+    b.push(qb);
+    qb.push(") OR (bl.id_bloqueador = ");
+    b.push(qb);
+    qb.push(" AND bl.id_bloqueado = ");
+    a.push(qb);
+    qb.push("))");
+}
+
+// Texto da condição para consultas montadas com format!; `a` e `b` são colunas ou parâmetros
+pub fn sem_bloqueio(a: &str, b: &str) -> String {
+    let mut qb = QueryBuilder::new("");
+    condicao(&mut qb, Lado::Coluna(a), Lado::Coluna(b));
+    qb.into_sql()
+}
+
+// Acrescenta à consulta a condição de que o usuário em `coluna` e `id_users` não se bloquearam
+pub fn filtrar(qb: &mut QueryBuilder<'_, Postgres>, coluna: &str, id_users: i32) {
+    qb.push(" AND ");
+    condicao(qb, Lado::Coluna(coluna), Lado::Id(id_users));
+}
+
+pub async fn bloqueados(
+    executor: impl sqlx::PgExecutor<'_>,
+    id1: i32,
+    id2: i32,
+) -> Result<bool, ApiError> {
+    let query = format!("SELECT {}", sem_bloqueio("$1", "$2"));
+
+    let livre = sqlx::query_scalar::<_, bool>(&query)
+        .bind(id1)
+        .bind(id2)
+        .fetch_one(executor)
+        .await?;
+    Ok(!livre)
+}
+
+// Registra o bloqueio, desfaz likes e match do par e cancela as sessões futuras entre os
+// dois. Devolve false se já existia
+pub async fn bloquear(
+    tx: &mut sqlx::PgConnection,
+    id_bloqueador: i32,
+    id_bloqueado: i32,
+) -> Result<bool, ApiError> {
+    // Mesma trava de /match/add_like: um like simultâneo não recria o match depois da remoção
+    sqlx::query("SELECT pg_advisory_xact_lock(LEAST($1, $2), GREATEST($1, $2))")
+        .bind(id_bloqueador)
+        .bind(id_bloqueado)
+        .execute(&mut *tx)
+        .await?;
+
+    let criado = sqlx::query(
+        r#"
+        INSERT INTO public.bloqueio (id_bloqueador, id_bloqueado)
+        VALUES ($1, $2)
+        ON CONFLICT (id_bloqueador, id_bloqueado) DO NOTHING
+        "#,
+    )
+    .bind(id_bloqueador)
+    .bind(id_bloqueado)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+
+    // A conversa e as mensagens ficam guardadas (podem servir de evidência numa denúncia),
+    // mas sem match o chat entre os dois fica indisponível
+    sqlx::query(
+        r#"
+        DELETE FROM public.teste_match
+        WHERE (id_deu_like = $1 AND id_liked = $2) OR (id_deu_like = $2 AND id_liked = $1)
+        "#,
+    )
+    .bind(id_bloqueador)
+    .bind(id_bloqueado)
+    .execute(&mut *tx)
+    .await?;
+
+    // Sessões que ainda não começaram são canceladas e os créditos retidos voltam aos
+    // alunos. As que já começaram não podem mais ser canceladas e seguem para a conclusão
+    let canceladas = sqlx::query_scalar::<_, i32>(
+        r#"
+        UPDATE public.sessao_troca
+        SET status = 'cancelada', id_aguardando = NULL, cancelada_por = $1,
+            sequencia = sequencia + 1, atualizada_em = now()
+        WHERE ((id_proponente = $1 AND id_convidado = $2) OR (id_proponente = $2 AND id_convidado = $1))
+          AND (status = 'proposta' OR (status = 'confirmada' AND inicio > now()))
+        RETURNING id
+        "#,
+    )
+    .bind(id_bloqueador)
+    .bind(id_bloqueado)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for id_sessao in canceladas {
+        custodia::estornar(tx, id_sessao).await?;
+    }
+
+    Ok(criado)
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct BloqueioRequest {
+    #[validate(range(min = 1, message = "ID de usuário inválido"))]
+    id_bloqueado: i32,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Bloqueio {
+    id_users: i32,
+    // Sem o sobrenome quando o usuário o deixou privado
+    full_name: String,
+    created_at: NaiveDateTime,
+}
+
+const SELECT_BLOQUEIO: &str = r#"
+    SELECT
+        u.id AS id_users,
+        CASE
+            WHEN u.sobrenome_publico THEN CONCAT(u.first_name, ' ', u.last_name)
+            ELSE u.first_name
+        END AS full_name,
+        b.created_at
+    FROM public.bloqueio b
+    JOIN public.users u ON u.id = b.id_bloqueado
+"#;
+
+// POST: Bloqueia um usuário; likes, match e sessões futuras entre os dois são desfeitos
+#[utoipa::path(
+    post,
+    path = "/bloqueios/usuario/{id_users}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário que bloqueia")
+    ),
+    request_body = BloqueioRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Usuário bloqueado", body = Bloqueio),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 409, description = "Usuário já bloqueado", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos ou usuário inexistente", body = ProblemDetails),
+        (status = 500, description = "Erro ao bloquear usuário", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn bloquear_usuario(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    dados: JsonValidado<BloqueioRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+    if dados.id_bloqueado == id_users {
+        return Err(ApiError::Validation(vec![FieldViolation {
+            field: "id_bloqueado".to_string(),
+            code: "self_reference".to_string(),
+            message: "O usuário não pode bloquear a si mesmo".to_string(),
+        }]));
+    }
+
+    Referencias::new(pool.get_ref())
+        .usuario("id_bloqueado", dados.id_bloqueado)
+        .await?
+        .verificar()?;
+
+    let mut tx = pool.begin().await?;
+    if !bloquear(&mut tx, id_users, dados.id_bloqueado).await? {
+        return Err(ApiError::Conflict("Usuário já bloqueado".to_string()));
+    }
+
+    let query = format!("{} WHERE b.id_bloqueador = $1 AND b.id_bloqueado = $2", SELECT_BLOQUEIO);
+    let bloqueio = sqlx::query_as::<_, Bloqueio>(&query)
+        .bind(id_users)
+        .bind(dados.id_bloqueado)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(HttpResponse::Created().json(bloqueio))
+}
+
+// GET: Usuários bloqueados, em ordem crescente de ID
+#[utoipa::path(
+    get,
+    path = "/bloqueios/usuario/{id_users}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário"),
+        PaginaQuery
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Usuários bloqueados", body = PaginaBloqueios),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar bloqueios", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn listar_bloqueios(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    params: web::Query<PaginaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+    validacao::validar(&*params)?;
+
+    let after = paginacao::decodificar::<i32>(params.after.as_deref())?;
+
+    let query = format!(
+        "{} WHERE b.id_bloqueador = $1 AND ($2::int4 IS NULL OR b.id_bloqueado > $2) ORDER BY b.id_bloqueado LIMIT $3",
+        SELECT_BLOQUEIO
+    );
+
+    let bloqueios = sqlx::query_as::<_, Bloqueio>(&query)
+        .bind(id_users)
+        .bind(after)
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(bloqueios, params.limit, |b| b.id_users)?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
+
+// DELETE: Desfaz o bloqueio; likes e match removidos no bloqueio não voltam
+#[utoipa::path(
+    delete,
+    path = "/bloqueios/usuario/{id_users}/{id_bloqueado}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário que bloqueou"),
+        ("id_bloqueado" = i32, Path, description = "ID do usuário bloqueado")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Bloqueio removido com sucesso"),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 404, description = "Bloqueio não encontrado", body = ProblemDetails),
+        (status = 500, description = "Erro ao remover bloqueio", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn desbloquear_usuario(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let (id_users, id_bloqueado) = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    let result = sqlx::query("DELETE FROM public.bloqueio WHERE id_bloqueador = $1 AND id_bloqueado = $2")
+        .bind(id_users)
+        .bind(id_bloqueado)
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound("Bloqueio não encontrado".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json("Bloqueio removido com sucesso"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sem_bloqueio_confere_os_dois_sentidos() {
+        assert_eq!(
+            sem_bloqueio("a.id", "$1"),
+            "NOT EXISTS (SELECT 1 FROM public.bloqueio bl WHERE \
+             (bl.id_bloqueador = a.id AND bl.id_bloqueado = $1) OR \
+             (bl.id_bloqueador = $1 AND bl.id_bloqueado = a.id))"
+        );
+    }
+
+    #[test]
+    fn filtrar_passa_o_id_como_parametro() {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT 1 FROM public.users u WHERE u.id <> ");
+        qb.push_bind(7);
+        filtrar(&mut qb, "u.id", 7);
+        assert_eq!(
+            qb.sql(),
+            "SELECT 1 FROM public.users u WHERE u.id <> $1 AND \
+             NOT EXISTS (SELECT 1 FROM public.bloqueio bl WHERE \
+             (bl.id_bloqueador = u.id AND bl.id_bloqueado = $2) OR \
+             (bl.id_bloqueador = $3 AND bl.id_bloqueado = u.id))"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use crate::auth::AuthUser;
+use crate::bloqueios;
+use crate::error::ApiError;
+use crate::ofertas;
+use crate::paginacao::{self, Pagina};
+use crate::validacao;
+use crate::DadosAll;
+
+// Pesos da relevância: o rank do full-text já pondera nome (A) acima de descrição (B);
+// a similaridade por trigramas cobre erros de digitação que o stemming não pega
+const PESO_SIMILARIDADE_NOME: f64 = 1.0;
+const PESO_SIMILARIDADE_DESCRICAO: f64 = 0.5;
+// Similaridade mínima para o operador <% (o padrão do pg_trgm, 0.6, descarta "vilao" → "violão")
+const LIMIAR_SIMILARIDADE: f64 = 0.4;
+
+#[derive(Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct BuscaQuery {
+    // Termos buscados no nome da sub-habilidade e na descrição da oferta
+    #[validate(
+        length(min = 2, max = 200, message = "A busca deve ter entre 2 e 200 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    q: String,
+    // Itens por página (1 a 100, padrão 20)
+    #[validate(range(min = 1, max = 100, message = "limit deve estar entre 1 e 100"))]
+    #[serde(default = "paginacao::limit_padrao")]
+    limit: i64,
+    // Valor de `next_cursor` recebido na página anterior
+    after: Option<String>,
+    id_sub_habilidade: Option<i32>,
+    id_habilidade: Option<i32>,
+    cidade: Option<String>,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ResultadoBusca {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    dados: DadosAll,
+    // Trecho da descrição com os termos encontrados entre <mark> e </mark>
+    trecho: String,
+    relevancia: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorBusca {
+    relevancia: f64,
+    id_users: i32,
+    id_sub_habilidade: i32,
+}
+
+// GET: Busca ofertas por palavra-chave, tolerando acentos e erros de digitação
+#[utoipa::path(
+    get,
+    path = "/buscar",
+    params(BuscaQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Ofertas encontradas, da mais para a menos relevante", body = PaginaBusca),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 422, description = "Busca ou parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar ofertas", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn buscar(
+    pool: web::Data<sqlx::PgPool>,
+    auth: AuthUser,
+    params: web::Query<BuscaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    validacao::validar(&*params)?;
+    let cursor = paginacao::decodificar::<CursorBusca>(params.after.as_deref())?;
+
+    let mut qb = QueryBuilder::<Postgres>::new(
+        r#"
+        WITH consulta AS (
+            SELECT
+                websearch_to_tsquery('public.portugues_sem_acento', "#,
+    );
+    qb.push_bind(params.q.trim().to_string());
+    qb.push(
+        r#") AS tsq,
+                public.f_unaccent(lower("#,
+    );
+    qb.push_bind(params.q.trim().to_string());
+    qb.push(
+        r#")) AS termo
+        ),
+        resultados AS (
+            SELECT
+                u.id_users,
+                us.first_name,
+                CASE WHEN us.sobrenome_publico THEN us.last_name END AS last_name,
+                u.id_sub_habilidade,
+                s.nome AS nome_sub_habilidade,
+                u.descricao,
+                u.valor,
+                u.created_at,
+                av.media AS media_avaliacao,
+                av.total AS total_avaliacoes,
+                (
+                    ts_rank(
+                        setweight(to_tsvector('public.portugues_sem_acento', s.nome), 'A')
+                        || setweight(to_tsvector('public.portugues_sem_acento', u.descricao), 'B'),
+                        c.tsq
+                    )
+                    + word_similarity(c.termo, public.f_unaccent(lower(s.nome))) * "#,
+    );
+    qb.push_bind(PESO_SIMILARIDADE_NOME);
+    qb.push(
+        r#"
+                    + word_similarity(c.termo, public.f_unaccent(lower(u.descricao))) * "#,
+    );
+    qb.push_bind(PESO_SIMILARIDADE_DESCRICAO);
+    qb.push(
+        r#"
+                )::float8 AS relevancia
+            FROM
+                public.usuario_sub_habilidade AS u
+            INNER JOIN
+                public.sub_habilidade AS s
+            ON
+                u.id_sub_habilidade = s.id
+            INNER JOIN
+                public.users AS us
+            ON
+                u.id_users = us.id"#,
+    );
+    qb.push(ofertas::JOIN_AVALIACOES);
+    qb.push(
+        r#"
+            CROSS JOIN
+                consulta AS c
+            WHERE (
+                to_tsvector('public.portugues_sem_acento', s.nome) @@ c.tsq
+                OR to_tsvector('public.portugues_sem_acento', u.descricao) @@ c.tsq
+                OR c.termo <% public.f_unaccent(lower(s.nome))
+                OR c.termo <% public.f_unaccent(lower(u.descricao))
+            )"#,
+    );
+
+    if let Some(id) = params.id_sub_habilidade {
+        qb.push(" AND u.id_sub_habilidade = ").push_bind(id);
+    }
+    if let Some(id) = params.id_habilidade {
+        qb.push(" AND s.id_habilidade = ").push_bind(id);
+    }
+    if let Some(cidade) = params.cidade.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+        // Cidade privada não pode ser descoberta pelo filtro
+        qb.push(" AND us.cidade_publica AND lower(us.city) = lower(")
+            .push_bind(cidade.to_string())
+            .push(")");
+    }
+    bloqueios::filtrar(&mut qb, "u.id_users", auth.id);
+
+    // O trecho só é gerado para a página devolvida: ts_headline é caro
+    qb.push(
+        r#"
+        )
+        SELECT
+            r.*,
+            ts_headline(
+                'public.portugues_sem_acento',
+                r.descricao,
+                c.tsq,
+                'StartSel=<mark>, StopSel=</mark>, MaxWords=20, MinWords=5, MaxFragments=2'
+            ) AS trecho
+        FROM (
+            SELECT * FROM resultados
+            WHERE TRUE"#,
+    );
+
+    if let Some(cursor) = cursor {
+        qb.push(" AND (relevancia < ")
+            .push_bind(cursor.relevancia)
+            .push(" OR (relevancia = ")
+            .push_bind(cursor.relevancia)
+            .push(" AND (id_users, id_sub_habilidade) > (")
+            .push_bind(cursor.id_users)
+            .push(", ")
+            .push_bind(cursor.id_sub_habilidade)
+            .push(")))");
+    }
+
+    qb.push(
+        r#"
+            ORDER BY relevancia DESC, id_users, id_sub_habilidade
+            LIMIT "#,
+    );
+    qb.push_bind(params.limit + 1);
+    qb.push(
+        r#"
+        ) AS r
+        CROSS JOIN consulta AS c
+        ORDER BY r.relevancia DESC, r.id_users, r.id_sub_habilidade
+    "#,
+    );
+
+    // O limiar vale só para esta transação
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)")
+        .bind(LIMIAR_SIMILARIDADE.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+    let resultados = qb
+        .build_query_as::<ResultadoBusca>()
+        .fetch_all(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    let pagina = Pagina::montar(resultados, params.limit, |r| CursorBusca {
+        relevancia: r.relevancia,
+        id_users: r.dados.id_users,
+        id_sub_habilidade: r.dados.id_sub_habilidade,
+    })?;
+
+    Ok(HttpResponse::Ok().json(pagina))
+}
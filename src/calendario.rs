@@ -0,0 +1,520 @@
+use actix_web::http::header::{
+    CacheControl, CacheDirective, ContentDisposition, DispositionParam, DispositionType,
+};
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::auth::{self, AuthUser};
+use crate::error::ApiError;
+use crate::sessoes::{Modalidade, StatusSessao};
+
+const TZID: &str = "America/Sao_Paulo";
+
+// O Brasil não tem horário de verão desde 2019: São Paulo fica sempre em -03:00
+const OFFSET_SAO_PAULO: i32 = -3 * 3600;
+
+// Sessões que começaram há mais tempo que isso saem do feed
+const DIAS_HISTORICO: i32 = 180;
+
+// Linhas do iCalendar com mais de 75 bytes são dobradas (RFC 5545, 3.1)
+const LARGURA_LINHA: usize = 75;
+
+const CONTENT_TYPE_ICS: &str = "text/calendar; charset=utf-8";
+
+#[derive(Serialize, ToSchema)]
+pub struct AssinaturaCalendario {
+    // Endereço do feed; o token só aparece nesta resposta
+    url: String,
+    created_at: NaiveDateTime,
+}
+
+// Sessão vista por um dos participantes, com o que cada lado ensina
+#[derive(sqlx::FromRow)]
+struct EventoSessao {
+    id: i32,
+    inicio: DateTime<Utc>,
+    duracao_minutos: i32,
+    modalidade: Modalidade,
+    local: Option<String>,
+    link: Option<String>,
+    status: StatusSessao,
+    sequencia: i32,
+    nome_outro_usuario: String,
+    ensina_usuario: Option<String>,
+    ensina_outro_usuario: Option<String>,
+}
+
+// Sessões do usuário $1; `filtro` completa o WHERE e pode usar $2 em diante
+fn select_evento(filtro: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            s.id, s.inicio, s.duracao_minutos, s.modalidade, s.local, s.link, s.status, s.sequencia,
+            CASE
+                WHEN u.sobrenome_publico THEN CONCAT(u.first_name, ' ', u.last_name)
+                ELSE u.first_name
+            END AS nome_outro_usuario,
+            CASE WHEN s.id_proponente = $1 THEN sp.nome ELSE sc.nome END AS ensina_usuario,
+            CASE WHEN s.id_proponente = $1 THEN sc.nome ELSE sp.nome END AS ensina_outro_usuario
+        FROM public.sessao_troca s
+        JOIN public.users u
+            ON u.id = CASE WHEN s.id_proponente = $1 THEN s.id_convidado ELSE s.id_proponente END
+        LEFT JOIN public.sub_habilidade sp ON sp.id = s.id_sub_habilidade_proponente
+        LEFT JOIN public.sub_habilidade sc ON sc.id = s.id_sub_habilidade_convidado
+        WHERE (s.id_proponente = $1 OR s.id_convidado = $1) AND {}
+        "#,
+        filtro
+    )
+}
+
+fn novo_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// POST: Gera o endereço privado do calendário do usuário, invalidando o anterior
+#[utoipa::path(
+    post,
+    path = "/calendario/usuario/{id_users}/token",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Novo endereço do feed .ics", body = AssinaturaCalendario),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 500, description = "Erro ao gerar endereço do calendário", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn gerar_assinatura(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    let token = novo_token();
+    let query = r#"
+        INSERT INTO public.calendario_assinatura (id_users, token_hash)
+        VALUES ($1, $2)
+        ON CONFLICT (id_users) DO UPDATE
+        SET token_hash = EXCLUDED.token_hash, created_at = now()
+        RETURNING created_at
+    "#;
+
+    let created_at = sqlx::query_scalar::<_, NaiveDateTime>(query)
+        .bind(id_users)
+        .bind(hash_token(&token))
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    let conexao = req.connection_info();
+    let url = format!(
+        "{}://{}/calendario/{}.ics",
+        conexao.scheme(),
+        conexao.host(),
+        token
+    );
+    Ok(HttpResponse::Created()
+        .insert_header(auth::sem_cache())
+        .json(AssinaturaCalendario { url, created_at }))
+}
+
+// DELETE: Revoga o endereço do calendário; o feed deixa de responder
+#[utoipa::path(
+    delete,
+    path = "/calendario/usuario/{id_users}/token",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Endereço revogado"),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 404, description = "O usuário não tem endereço de calendário", body = ProblemDetails),
+        (status = 500, description = "Erro ao revogar endereço do calendário", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn revogar_assinatura(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    let resultado = sqlx::query("DELETE FROM public.calendario_assinatura WHERE id_users = $1")
+        .bind(id_users)
+        .execute(pool.get_ref())
+        .await?;
+
+    if resultado.rows_affected() == 0 {
+        return Err(ApiError::NotFound(
+            "O usuário não tem endereço de calendário".to_string(),
+        ));
+    }
+    Ok(HttpResponse::Ok().json("Endereço do calendário revogado com sucesso"))
+}
+
+// GET: Feed .ics com as sessões confirmadas do usuário; o token da URL substitui o login
+#[utoipa::path(
+    get,
+    path = "/calendario/{token}.ics",
+    params(
+        ("token" = String, Path, description = "Token gerado em /calendario/usuario/{id_users}/token")
+    ),
+    responses(
+        (status = 200, description = "Calendário no formato iCalendar", body = String, content_type = "text/calendar"),
+        (status = 404, description = "Endereço inválido ou revogado", body = ProblemDetails),
+        (status = 500, description = "Erro ao gerar calendário", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn feed_calendario(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = sqlx::query_scalar::<_, i32>(
+        "SELECT id_users FROM public.calendario_assinatura WHERE token_hash = $1",
+    )
+    .bind(hash_token(&path.into_inner()))
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Endereço de calendário inválido ou revogado".to_string()))?;
+
+    // Sessões canceladas ou reagendadas depois de confirmadas continuam no feed,
+    // com STATUS:CANCELLED ou TENTATIVE, para os calendários atualizarem o evento
+    let query = format!(
+        "{} ORDER BY s.inicio, s.id",
+        select_evento("s.confirmada_em IS NOT NULL AND s.inicio >= now() - make_interval(days => $2)")
+    );
+
+    let eventos = sqlx::query_as::<_, EventoSessao>(&query)
+        .bind(id_users)
+        .bind(DIAS_HISTORICO)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(CONTENT_TYPE_ICS)
+        .insert_header(CacheControl(vec![CacheDirective::Private, CacheDirective::NoCache]))
+        .body(gerar_ics(&eventos)))
+}
+
+// GET: Arquivo .ics avulso de uma sessão, para importar no calendário
+#[utoipa::path(
+    get,
+    path = "/sessoes/{id}/calendario.ics",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sessão no formato iCalendar", body = String, content_type = "text/calendar"),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 500, description = "Erro ao gerar calendário", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn baixar_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+
+    let (id_proponente, id_convidado) = sqlx::query_as::<_, (i32, i32)>(
+        "SELECT id_proponente, id_convidado FROM public.sessao_troca WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Sessão não encontrada".to_string()))?;
+
+    auth.garantir_participante(id_proponente, id_convidado)?;
+
+    let evento = sqlx::query_as::<_, EventoSessao>(&select_evento("s.id = $2"))
+        .bind(auth.id)
+        .bind(id)
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(CONTENT_TYPE_ICS)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("sessao-{}.ics", id))],
+        })
+        .body(gerar_ics(&[evento])))
+}
+
+fn gerar_ics(eventos: &[EventoSessao]) -> String {
+    let mut ics = String::new();
+    for linha in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Swapp//Sessoes de troca//PT-BR",
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+        "X-WR-CALNAME:Swapp",
+        "X-WR-TIMEZONE:America/Sao_Paulo",
+        "REFRESH-INTERVAL;VALUE=DURATION:PT1H",
+        "X-PUBLISHED-TTL:PT1H",
+        "BEGIN:VTIMEZONE",
+        "TZID:America/Sao_Paulo",
+        "BEGIN:STANDARD",
+        "DTSTART:19700101T000000",
+        "TZOFFSETFROM:-0300",
+        "TZOFFSETTO:-0300",
+        "TZNAME:-03",
+        "END:STANDARD",
+        "END:VTIMEZONE",
+    ] {
+        adicionar_linha(&mut ics, linha);
+    }
+
+    let agora = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    for evento in eventos {
+        adicionar_evento(&mut ics, evento, &agora);
+    }
+
+    adicionar_linha(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+fn adicionar_evento(ics: &mut String, evento: &EventoSessao, agora: &str) {
+    let fim = evento.inicio + Duration::minutes(evento.duracao_minutos.into());
+
+    let (status, prefixo) = match evento.status {
+        StatusSessao::Confirmada | StatusSessao::Concluida | StatusSessao::NaoCompareceu => {
+            ("CONFIRMED", "")
+        }
+        // Reagendada depois de confirmada, aguardando uma nova resposta
+        StatusSessao::Proposta => ("TENTATIVE", "A confirmar: "),
+        StatusSessao::Cancelada | StatusSessao::Recusada => ("CANCELLED", "Cancelada: "),
+    };
+
+    let mut descricao = Vec::new();
+    if let Some(nome) = &evento.ensina_usuario {
+        descricao.push(format!("Você ensina: {}", nome));
+    }
+    if let Some(nome) = &evento.ensina_outro_usuario {
+        descricao.push(format!("{} ensina: {}", evento.nome_outro_usuario, nome));
+    }
+
+    let mut linhas = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:sessao-{}@swapp", evento.id),
+        format!("DTSTAMP:{}", agora),
+        format!("DTSTART;TZID={}:{}", TZID, horario_local(evento.inicio)),
+        format!("DTEND;TZID={}:{}", TZID, horario_local(fim)),
+        format!(
+            "SUMMARY:{}",
+            escapar(&format!("{}Troca com {}", prefixo, evento.nome_outro_usuario))
+        ),
+        format!("STATUS:{}", status),
+        format!("SEQUENCE:{}", evento.sequencia),
+    ];
+    match evento.modalidade {
+        Modalidade::Presencial => {
+            if let Some(local) = &evento.local {
+                linhas.push(format!("LOCATION:{}", escapar(local)));
+            }
+        }
+        Modalidade::Online => {
+            if let Some(link) = &evento.link {
+                descricao.push(format!("Link: {}", link));
+                linhas.push(format!("LOCATION:{}", escapar(link)));
+                linhas.push(format!("URL:{}", link));
+            }
+        }
+    }
+    if !descricao.is_empty() {
+        linhas.push(format!("DESCRIPTION:{}", escapar(&descricao.join("\n"))));
+    }
+    linhas.push("END:VEVENT".to_string());
+
+    for linha in linhas {
+        adicionar_linha(ics, &linha);
+    }
+}
+
+fn horario_local(instante: DateTime<Utc>) -> String {
+    let fuso = FixedOffset::east_opt(OFFSET_SAO_PAULO).expect("offset de São Paulo é válido");
+    instante
+        .with_timezone(&fuso)
+        .format("%Y%m%dT%H%M%S")
+        .to_string()
+}
+
+// Escapa texto conforme a RFC 5545 (3.3.11)
+fn escapar(texto: &str) -> String {
+    let mut escapado = String::with_capacity(texto.len());
+    for c in texto.chars() {
+        match c {
+            '\\' => escapado.push_str("\\\\"),
+            ';' => escapado.push_str("\\;"),
+            ',' => escapado.push_str("\\,"),
+            '\n' => escapado.push_str("\\n"),
+            '\r' => {}
+            c => escapado.push(c),
+        }
+    }
+    escapado
+}
+
+// Termina a linha com CRLF, dobrando-a sem quebrar caracteres UTF-8 ao meio
+fn adicionar_linha(ics: &mut String, linha: &str) {
+    let mut largura = 0;
+    for c in linha.chars() {
+        if largura + c.len_utf8() > LARGURA_LINHA {
+            ics.push_str("\r\n ");
+            largura = 1;
+        }
+        ics.push(c);
+        largura += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn evento(status: StatusSessao, sequencia: i32) -> EventoSessao {
+        EventoSessao {
+            id: 42,
+            inicio: Utc.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap(),
+            duracao_minutos: 90,
+            modalidade: Modalidade::Presencial,
+            local: Some("Café, Rua A; sala 2".to_string()),
+            link: None,
+            status,
+            sequencia,
+            nome_outro_usuario: "Bruno".to_string(),
+            ensina_usuario: Some("Violão".to_string()),
+            ensina_outro_usuario: Some("Inglês".to_string()),
+        }
+    }
+
+    // Desfaz a dobra das linhas e devolve as linhas lógicas do arquivo
+    fn linhas(ics: &str) -> Vec<String> {
+        ics.replace("\r\n ", "")
+            .split("\r\n")
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn dobra_linhas_longas_sem_quebrar_caracteres() {
+        let linha = format!("SUMMARY:{}", "çã".repeat(60));
+        let mut ics = String::new();
+        adicionar_linha(&mut ics, &linha);
+
+        assert!(ics.ends_with("\r\n"));
+        let fisicas: Vec<&str> = ics.trim_end_matches("\r\n").split("\r\n").collect();
+        // "SUMMARY:" tem 8 bytes e cada letra acentuada 2: a primeira dobra fica em 74 bytes
+        assert_eq!(fisicas[0].len(), 74);
+        for (i, fisica) in fisicas.iter().enumerate() {
+            assert!(fisica.len() <= LARGURA_LINHA, "linha {} com {} bytes", i, fisica.len());
+            // A continuação começa com um espaço, que não faz parte do conteúdo
+            assert_eq!(i > 0, fisica.starts_with(' '));
+        }
+        assert_eq!(linhas(&ics), vec![linha]);
+    }
+
+    #[test]
+    fn linha_com_75_bytes_nao_e_dobrada() {
+        let linha = "X".repeat(LARGURA_LINHA);
+        let mut ics = String::new();
+        adicionar_linha(&mut ics, &linha);
+        assert_eq!(ics, format!("{}\r\n", linha));
+    }
+
+    #[test]
+    fn escapa_caracteres_especiais() {
+        assert_eq!(escapar("a\\b;c,d\r\ne"), r"a\\b\;c\,d\ne");
+        assert_eq!(escapar("sem especiais"), "sem especiais");
+    }
+
+    #[test]
+    fn horario_local_em_sao_paulo() {
+        let instante = Utc.with_ymd_and_hms(2024, 1, 1, 2, 30, 0).unwrap();
+        // Volta para o dia anterior em -03:00
+        assert_eq!(horario_local(instante), "20231231T233000");
+    }
+
+    #[test]
+    fn evento_confirmado() {
+        let ics = gerar_ics(&[evento(StatusSessao::Confirmada, 0)]);
+        let linhas = linhas(&ics);
+
+        for esperada in [
+            "UID:sessao-42@swapp",
+            "DTSTART;TZID=America/Sao_Paulo:20240610T090000",
+            "DTEND;TZID=America/Sao_Paulo:20240610T103000",
+            "SUMMARY:Troca com Bruno",
+            "STATUS:CONFIRMED",
+            "SEQUENCE:0",
+            r"LOCATION:Café\, Rua A\; sala 2",
+            r"DESCRIPTION:Você ensina: Violão\nBruno ensina: Inglês",
+        ] {
+            assert!(linhas.iter().any(|l| l == esperada), "faltou {:?}", esperada);
+        }
+        assert_eq!(linhas.first().map(String::as_str), Some("BEGIN:VCALENDAR"));
+        assert_eq!(linhas.last().map(String::as_str), Some("END:VCALENDAR"));
+    }
+
+    #[test]
+    fn reagendamento_e_cancelamento_mudam_status_e_sequencia() {
+        let reagendada = linhas(&gerar_ics(&[evento(StatusSessao::Proposta, 1)]));
+        assert!(reagendada.contains(&"STATUS:TENTATIVE".to_string()));
+        assert!(reagendada.contains(&"SEQUENCE:1".to_string()));
+        assert!(reagendada.contains(&"SUMMARY:A confirmar: Troca com Bruno".to_string()));
+
+        for status in [StatusSessao::Cancelada, StatusSessao::Recusada] {
+            let cancelada = linhas(&gerar_ics(&[evento(status, 2)]));
+            assert!(cancelada.contains(&"STATUS:CANCELLED".to_string()));
+            assert!(cancelada.contains(&"SEQUENCE:2".to_string()));
+            assert!(cancelada.contains(&"SUMMARY:Cancelada: Troca com Bruno".to_string()));
+        }
+
+        for status in [StatusSessao::Concluida, StatusSessao::NaoCompareceu] {
+            let encerrada = linhas(&gerar_ics(&[evento(status, 3)]));
+            assert!(encerrada.contains(&"STATUS:CONFIRMED".to_string()));
+        }
+    }
+
+    #[test]
+    fn sessao_online_usa_o_link() {
+        let mut online = evento(StatusSessao::Confirmada, 0);
+        online.modalidade = Modalidade::Online;
+        online.link = Some("https://meet.example.com/abc".to_string());
+        let linhas = linhas(&gerar_ics(&[online]));
+
+        assert!(linhas.contains(&"URL:https://meet.example.com/abc".to_string()));
+        assert!(linhas.contains(&"LOCATION:https://meet.example.com/abc".to_string()));
+        assert!(!linhas.iter().any(|l| l.starts_with("LOCATION:Café")));
+    }
+}
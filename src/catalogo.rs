@@ -0,0 +1,806 @@
+use actix_web::http::header::{
+    self, CacheDirective, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch,
+};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, PgPool};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::auth::Admin;
+use crate::error::{ApiError, FieldViolation};
+use crate::validacao::{self, JsonValidado, Referencias};
+
+#[derive(Serialize, ToSchema)]
+pub struct CategoriaCatalogo {
+    id: i32,
+    nome: String,
+    // Soma das ofertas das sub-habilidades da categoria
+    ofertas: i64,
+    sub_habilidades: Vec<SubHabilidadeCatalogo>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SubHabilidadeCatalogo {
+    id: i32,
+    nome: String,
+    // Quantidade de usuários que oferecem a sub-habilidade
+    ofertas: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct LinhaCatalogo {
+    id_habilidade: i32,
+    nome_habilidade: String,
+    id_sub_habilidade: Option<i32>,
+    nome_sub_habilidade: Option<String>,
+    ofertas: i64,
+}
+
+// GET: Retorna a árvore habilidade → sub-habilidade ativas com a contagem de ofertas
+#[utoipa::path(
+    get,
+    path = "/catalogo",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "ETag recebido na última resposta"),
+        ("If-Modified-Since" = Option<String>, Header, description = "Last-Modified recebido na última resposta")
+    ),
+    responses(
+        (status = 200, description = "Catálogo completo, com headers ETag e Last-Modified", body = [CategoriaCatalogo]),
+        (status = 304, description = "O catálogo não mudou desde a versão do cliente"),
+        (status = 500, description = "Erro ao buscar catálogo", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_catalogo(
+    pool: web::Data<sqlx::PgPool>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    // Leitura consistente: a marca de tempo e as contagens vêm do mesmo snapshot
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let atualizado_em = sqlx::query_scalar::<_, DateTime<Utc>>(
+        "SELECT atualizado_em FROM public.catalogo_estado",
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let query = r#"
+        SELECT
+            h.id AS id_habilidade,
+            h.nome AS nome_habilidade,
+            s.id AS id_sub_habilidade,
+            s.nome AS nome_sub_habilidade,
+            COUNT(u.id_users) AS ofertas
+        FROM
+            public.habilidade AS h
+        LEFT JOIN
+            public.sub_habilidade AS s
+        ON
+            s.id_habilidade = h.id AND s.ativo
+        LEFT JOIN
+            public.usuario_sub_habilidade AS u
+        ON
+            u.id_sub_habilidade = s.id
+        WHERE h.ativo
+        GROUP BY h.id, h.nome, s.id, s.nome
+        ORDER BY h.nome, h.id, s.nome, s.id
+    "#;
+
+    let linhas = sqlx::query_as::<_, LinhaCatalogo>(query)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    let catalogo = montar_arvore(linhas);
+    let corpo = serde_json::to_vec(&catalogo).map_err(|e| {
+        log::error!("Erro ao serializar catálogo: {:?}", e);
+        ApiError::Internal
+    })?;
+
+    let etag = EntityTag::new_strong(hex::encode(&Sha256::digest(&corpo)[..16]));
+    // HTTP-date tem resolução de segundos
+    let last_modified =
+        UNIX_EPOCH + Duration::from_secs(atualizado_em.timestamp().max(0) as u64);
+
+    let modificado = !nao_modificado(&req, &etag, last_modified);
+
+    let mut resposta = if modificado {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::NotModified()
+    };
+    resposta
+        .insert_header(header::ETag(etag))
+        .insert_header(header::LastModified(HttpDate::from(last_modified)))
+        // O cliente pode guardar, mas deve revalidar a cada uso
+        .insert_header(header::CacheControl(vec![CacheDirective::NoCache]));
+
+    if !modificado {
+        return Ok(resposta.finish());
+    }
+
+    Ok(resposta.content_type(header::ContentType::json()).body(corpo))
+}
+
+fn montar_arvore(linhas: Vec<LinhaCatalogo>) -> Vec<CategoriaCatalogo> {
+    let mut catalogo: Vec<CategoriaCatalogo> = Vec::new();
+
+    for linha in linhas {
+        // As linhas chegam agrupadas por categoria
+        if catalogo.last().map(|c| c.id) != Some(linha.id_habilidade) {
+            catalogo.push(CategoriaCatalogo {
+                id: linha.id_habilidade,
+                nome: linha.nome_habilidade,
+                ofertas: 0,
+                sub_habilidades: Vec::new(),
+            });
+        }
+        let categoria = catalogo.last_mut().expect("categoria recém-inserida");
+
+        // Categoria sem sub-habilidades vem com as colunas do LEFT JOIN nulas
+        if let (Some(id), Some(nome)) = (linha.id_sub_habilidade, linha.nome_sub_habilidade) {
+            categoria.ofertas += linha.ofertas;
+            categoria.sub_habilidades.push(SubHabilidadeCatalogo {
+                id,
+                nome,
+                ofertas: linha.ofertas,
+            });
+        }
+    }
+
+    catalogo
+}
+
+// If-None-Match tem precedência; If-Modified-Since só vale quando não há ETag na requisição
+fn nao_modificado(req: &HttpRequest, etag: &EntityTag, last_modified: SystemTime) -> bool {
+    if let Some(if_none_match) = req.get_header::<IfNoneMatch>() {
+        return match if_none_match {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Items(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        };
+    }
+
+    match req.get_header::<IfModifiedSince>() {
+        Some(IfModifiedSince(desde)) => last_modified <= SystemTime::from(desde),
+        None => false,
+    }
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct HabilidadeAdmin {
+    id: i32,
+    nome: String,
+    ativo: bool,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct SubHabilidadeAdmin {
+    id: i32,
+    id_habilidade: i32,
+    nome: String,
+    ativo: bool,
+}
+
+// Catálogo completo para administração, incluindo entradas desativadas
+#[derive(Serialize, ToSchema)]
+pub struct CatalogoAdmin {
+    habilidades: Vec<HabilidadeAdmin>,
+    sub_habilidades: Vec<SubHabilidadeAdmin>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct HabilidadeRequest {
+    #[validate(
+        length(min = 1, max = 100, message = "O nome deve ter entre 1 e 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    nome: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AtualizarHabilidadeRequest {
+    // Novo nome; ausente mantém o atual
+    #[validate(
+        length(min = 1, max = 100, message = "O nome deve ter entre 1 e 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    nome: Option<String>,
+    // false desativa, true reativa
+    ativo: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct SubHabilidadeRequest {
+    #[validate(range(min = 1, message = "ID de habilidade inválido"))]
+    id_habilidade: i32,
+    #[validate(
+        length(min = 1, max = 100, message = "O nome deve ter entre 1 e 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    nome: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AtualizarSubHabilidadeRequest {
+    #[validate(
+        length(min = 1, max = 100, message = "O nome deve ter entre 1 e 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    nome: Option<String>,
+    // Move a sub-habilidade para outra categoria
+    #[validate(range(min = 1, message = "ID de habilidade inválido"))]
+    id_habilidade: Option<i32>,
+    ativo: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct MesclarRequest {
+    // Sub-habilidade que recebe as ofertas e desejos da sub-habilidade do path
+    #[validate(range(min = 1, message = "ID de sub-habilidade inválido"))]
+    id_destino: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MesclagemResponse {
+    ofertas_movidas: u64,
+    // Ofertas removidas porque o usuário já oferecia a sub-habilidade de destino
+    ofertas_descartadas: u64,
+    desejadas_movidas: u64,
+    desejadas_descartadas: u64,
+}
+
+// Nomes são únicos sem diferenciar maiúsculas nem acentos; `exceto` ignora a própria entrada ao renomear
+async fn garantir_nome_habilidade_livre(
+    pool: &PgPool,
+    nome: &str,
+    exceto: Option<i32>,
+) -> Result<(), ApiError> {
+    let query = r#"
+        SELECT EXISTS (
+            SELECT 1 FROM public.habilidade
+            WHERE public.f_unaccent(lower(nome)) = public.f_unaccent(lower($1))
+              AND ($2::int4 IS NULL OR id <> $2)
+        )
+    "#;
+
+    let em_uso = sqlx::query_scalar::<_, bool>(query)
+        .bind(nome)
+        .bind(exceto)
+        .fetch_one(pool)
+        .await?;
+
+    if em_uso {
+        return Err(ApiError::Conflict(format!("Já existe uma habilidade chamada \"{}\"", nome)));
+    }
+    Ok(())
+}
+
+pub(crate) async fn garantir_nome_sub_habilidade_livre(
+    executor: impl PgExecutor<'_>,
+    id_habilidade: i32,
+    nome: &str,
+    exceto: Option<i32>,
+) -> Result<(), ApiError> {
+    let query = r#"
+        SELECT EXISTS (
+            SELECT 1 FROM public.sub_habilidade
+            WHERE id_habilidade = $1
+              AND public.f_unaccent(lower(nome)) = public.f_unaccent(lower($2))
+              AND ($3::int4 IS NULL OR id <> $3)
+        )
+    "#;
+
+    let em_uso = sqlx::query_scalar::<_, bool>(query)
+        .bind(id_habilidade)
+        .bind(nome)
+        .bind(exceto)
+        .fetch_one(executor)
+        .await?;
+
+    if em_uso {
+        return Err(ApiError::Conflict(format!(
+            "Já existe uma sub-habilidade chamada \"{}\" nesta habilidade",
+            nome
+        )));
+    }
+    Ok(())
+}
+
+// GET: Retorna todo o catálogo, inclusive entradas desativadas (somente admin)
+#[utoipa::path(
+    get,
+    path = "/admin/catalogo",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Habilidades e sub-habilidades. Requer papel admin.", body = CatalogoAdmin),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel admin", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar catálogo", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_catalogo_admin(
+    pool: web::Data<sqlx::PgPool>,
+    _admin: Admin,
+) -> Result<HttpResponse, ApiError> {
+    let habilidades = sqlx::query_as::<_, HabilidadeAdmin>(
+        "SELECT id, nome, ativo FROM public.habilidade ORDER BY nome, id",
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let sub_habilidades = sqlx::query_as::<_, SubHabilidadeAdmin>(
+        "SELECT id, id_habilidade, nome, ativo FROM public.sub_habilidade ORDER BY id_habilidade, nome, id",
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CatalogoAdmin {
+        habilidades,
+        sub_habilidades,
+    }))
+}
+
+// POST: Cria uma habilidade (categoria)
+#[utoipa::path(
+    post,
+    path = "/admin/habilidades",
+    request_body = HabilidadeRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Habilidade criada. Requer papel admin.", body = HabilidadeAdmin),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel admin", body = ProblemDetails),
+        (status = 409, description = "Já existe uma habilidade com esse nome", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao criar habilidade", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn criar_habilidade(
+    pool: web::Data<sqlx::PgPool>,
+    Admin(admin): Admin,
+    dados: JsonValidado<HabilidadeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let nome = dados.nome.trim();
+    garantir_nome_habilidade_livre(pool.get_ref(), nome, None).await?;
+
+    let habilidade = sqlx::query_as::<_, HabilidadeAdmin>(
+        "INSERT INTO public.habilidade (nome) VALUES ($1) RETURNING id, nome, ativo",
+    )
+    .bind(nome)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    log::info!("Habilidade {} criada pelo admin {}", habilidade.id, admin.id);
+    Ok(HttpResponse::Created().json(habilidade))
+}
+
+// PUT: Renomeia, desativa ou reativa uma habilidade
+#[utoipa::path(
+    put,
+    path = "/admin/habilidades/{id}",
+    params(
+        ("id" = i32, Path, description = "ID da habilidade")
+    ),
+    request_body = AtualizarHabilidadeRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Habilidade atualizada. Requer papel admin.", body = HabilidadeAdmin),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel admin", body = ProblemDetails),
+        (status = 404, description = "Habilidade não encontrada", body = ProblemDetails),
+        (status = 409, description = "Já existe uma habilidade com esse nome", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao atualizar habilidade", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn atualizar_habilidade(
+    pool: web::Data<sqlx::PgPool>,
+    Admin(admin): Admin,
+    path: web::Path<i32>,
+    dados: JsonValidado<AtualizarHabilidadeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let nome = dados.nome.as_deref().map(str::trim);
+
+    if let Some(nome) = nome {
+        garantir_nome_habilidade_livre(pool.get_ref(), nome, Some(id)).await?;
+    }
+
+    let query = r#"
+        UPDATE public.habilidade
+        SET nome = COALESCE($1, nome), ativo = COALESCE($2, ativo)
+        WHERE id = $3
+        RETURNING id, nome, ativo
+    "#;
+
+    let habilidade = sqlx::query_as::<_, HabilidadeAdmin>(query)
+        .bind(nome)
+        .bind(dados.ativo)
+        .bind(id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Habilidade não encontrada".to_string()))?;
+
+    log::info!("Habilidade {} atualizada pelo admin {}", id, admin.id);
+    Ok(HttpResponse::Ok().json(habilidade))
+}
+
+// POST: Cria uma sub-habilidade dentro de uma habilidade
+#[utoipa::path(
+    post,
+    path = "/admin/sub_habilidades",
+    request_body = SubHabilidadeRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Sub-habilidade criada. Requer papel admin.", body = SubHabilidadeAdmin),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel admin", body = ProblemDetails),
+        (status = 409, description = "Já existe uma sub-habilidade com esse nome na habilidade", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos ou habilidade inexistente", body = ProblemDetails),
+        (status = 500, description = "Erro ao criar sub-habilidade", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn criar_sub_habilidade(
+    pool: web::Data<sqlx::PgPool>,
+    Admin(admin): Admin,
+    dados: JsonValidado<SubHabilidadeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let nome = dados.nome.trim();
+
+    Referencias::new(pool.get_ref())
+        .habilidade("id_habilidade", dados.id_habilidade)
+        .await?
+        .verificar()?;
+    garantir_nome_sub_habilidade_livre(pool.get_ref(), dados.id_habilidade, nome, None).await?;
+
+    let query = r#"
+        INSERT INTO public.sub_habilidade (id_habilidade, nome)
+        VALUES ($1, $2)
+        RETURNING id, id_habilidade, nome, ativo
+    "#;
+
+    let sub_habilidade = sqlx::query_as::<_, SubHabilidadeAdmin>(query)
+        .bind(dados.id_habilidade)
+        .bind(nome)
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    log::info!("Sub-habilidade {} criada pelo admin {}", sub_habilidade.id, admin.id);
+    Ok(HttpResponse::Created().json(sub_habilidade))
+}
+
+// PUT: Renomeia, move de habilidade, desativa ou reativa uma sub-habilidade
+#[utoipa::path(
+    put,
+    path = "/admin/sub_habilidades/{id}",
+    params(
+        ("id" = i32, Path, description = "ID da sub-habilidade")
+    ),
+    request_body = AtualizarSubHabilidadeRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sub-habilidade atualizada. Requer papel admin.", body = SubHabilidadeAdmin),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel admin", body = ProblemDetails),
+        (status = 404, description = "Sub-habilidade não encontrada", body = ProblemDetails),
+        (status = 409, description = "Já existe uma sub-habilidade com esse nome na habilidade de destino", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos ou habilidade inexistente", body = ProblemDetails),
+        (status = 500, description = "Erro ao atualizar sub-habilidade", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn atualizar_sub_habilidade(
+    pool: web::Data<sqlx::PgPool>,
+    Admin(admin): Admin,
+    path: web::Path<i32>,
+    dados: JsonValidado<AtualizarSubHabilidadeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+
+    let atual = sqlx::query_as::<_, SubHabilidadeAdmin>(
+        "SELECT id, id_habilidade, nome, ativo FROM public.sub_habilidade WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Sub-habilidade não encontrada".to_string()))?;
+
+    if let Some(id_habilidade) = dados.id_habilidade {
+        Referencias::new(pool.get_ref())
+            .habilidade("id_habilidade", id_habilidade)
+            .await?
+            .verificar()?;
+    }
+
+    // Renomear ou mover pode colidir com um nome já usado na habilidade de destino
+    let id_habilidade = dados.id_habilidade.unwrap_or(atual.id_habilidade);
+    let nome = dados.nome.as_deref().map(str::trim).unwrap_or(&atual.nome);
+    if id_habilidade != atual.id_habilidade || nome != atual.nome {
+        garantir_nome_sub_habilidade_livre(pool.get_ref(), id_habilidade, nome, Some(id)).await?;
+    }
+
+    let query = r#"
+        UPDATE public.sub_habilidade
+        SET id_habilidade = $1, nome = $2, ativo = COALESCE($3, ativo)
+        WHERE id = $4
+        RETURNING id, id_habilidade, nome, ativo
+    "#;
+
+    let sub_habilidade = sqlx::query_as::<_, SubHabilidadeAdmin>(query)
+        .bind(id_habilidade)
+        .bind(nome)
+        .bind(dados.ativo)
+        .bind(id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Sub-habilidade não encontrada".to_string()))?;
+
+    log::info!("Sub-habilidade {} atualizada pelo admin {}", id, admin.id);
+    Ok(HttpResponse::Ok().json(sub_habilidade))
+}
+
+// POST: Funde uma sub-habilidade duplicada em outra e remove a duplicata
+#[utoipa::path(
+    post,
+    path = "/admin/sub_habilidades/{id}/mesclar",
+    params(
+        ("id" = i32, Path, description = "ID da sub-habilidade duplicada, que será removida")
+    ),
+    request_body = MesclarRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Ofertas e desejos movidos para a sub-habilidade de destino. Requer papel admin.", body = MesclagemResponse),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel admin", body = ProblemDetails),
+        (status = 404, description = "Sub-habilidade não encontrada", body = ProblemDetails),
+        (status = 422, description = "Destino inexistente ou igual à origem", body = ProblemDetails),
+        (status = 500, description = "Erro ao mesclar sub-habilidades", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn mesclar_sub_habilidade(
+    pool: web::Data<sqlx::PgPool>,
+    Admin(admin): Admin,
+    path: web::Path<i32>,
+    dados: JsonValidado<MesclarRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let origem = path.into_inner();
+    let destino = dados.id_destino;
+
+    if origem == destino {
+        return Err(ApiError::Validation(vec![FieldViolation {
+            field: "id_destino".to_string(),
+            code: "self_reference".to_string(),
+            message: "A sub-habilidade não pode ser mesclada nela mesma".to_string(),
+        }]));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Trava as duas linhas para que ninguém renomeie ou mescle as mesmas entradas em paralelo
+    let travadas = sqlx::query_scalar::<_, i32>(
+        "SELECT id FROM public.sub_habilidade WHERE id IN ($1, $2) ORDER BY id FOR UPDATE",
+    )
+    .bind(origem)
+    .bind(destino)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if !travadas.contains(&origem) {
+        return Err(ApiError::NotFound("Sub-habilidade não encontrada".to_string()));
+    }
+    if !travadas.contains(&destino) {
+        return Err(ApiError::Validation(vec![FieldViolation {
+            field: "id_destino".to_string(),
+            code: "not_found".to_string(),
+            message: "Sub-habilidade não encontrada".to_string(),
+        }]));
+    }
+
+    let mut resposta = MesclagemResponse {
+        ofertas_movidas: 0,
+        ofertas_descartadas: 0,
+        desejadas_movidas: 0,
+        desejadas_descartadas: 0,
+    };
+
+    // Imagens das ofertas que serão descartadas vão para a oferta equivalente no destino;
+    // as das ofertas movidas acompanham a oferta pelo ON UPDATE CASCADE
+    sqlx::query(
+        r#"
+        UPDATE public.portfolio_imagem AS p
+        SET id_sub_habilidade = $2
+        WHERE p.id_sub_habilidade = $1
+          AND EXISTS (
+              SELECT 1 FROM public.usuario_sub_habilidade AS d
+              WHERE d.id_users = p.id_users AND d.id_sub_habilidade = $2
+          )
+        "#,
+    )
+    .bind(origem)
+    .bind(destino)
+    .execute(&mut *tx)
+    .await?;
+
+    // Mesma estratégia nas duas tabelas: move o que não colide e descarta o que sobrou,
+    // já que o usuário tem a linha equivalente no destino
+    for (tabela, movidas, descartadas) in [
+        (
+            "usuario_sub_habilidade",
+            &mut resposta.ofertas_movidas,
+            &mut resposta.ofertas_descartadas,
+        ),
+        (
+            "usuario_sub_habilidade_desejada",
+            &mut resposta.desejadas_movidas,
+            &mut resposta.desejadas_descartadas,
+        ),
+    ] {
+        let mover = format!(
+            r#"
+            UPDATE public.{tabela} AS o
+            SET id_sub_habilidade = $2
+            WHERE o.id_sub_habilidade = $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM public.{tabela} AS d
+                  WHERE d.id_users = o.id_users AND d.id_sub_habilidade = $2
+              )
+            "#
+        );
+        *movidas = sqlx::query(&mover)
+            .bind(origem)
+            .bind(destino)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        let descartar = format!("DELETE FROM public.{tabela} WHERE id_sub_habilidade = $1");
+        *descartadas = sqlx::query(&descartar)
+            .bind(origem)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+
+    // Sugestões já decididas passam a apontar para o destino
+    sqlx::query(
+        "UPDATE public.sugestao_sub_habilidade SET id_sub_habilidade = $2 WHERE id_sub_habilidade = $1",
+    )
+    .bind(origem)
+    .bind(destino)
+    .execute(&mut *tx)
+    .await?;
+
+    // Sessões de troca guardam o que cada lado ensina
+    sqlx::query(
+        r#"
+        UPDATE public.sessao_troca
+        SET id_sub_habilidade_proponente = CASE WHEN id_sub_habilidade_proponente = $1 THEN $2 ELSE id_sub_habilidade_proponente END,
+            id_sub_habilidade_convidado = CASE WHEN id_sub_habilidade_convidado = $1 THEN $2 ELSE id_sub_habilidade_convidado END
+        WHERE id_sub_habilidade_proponente = $1 OR id_sub_habilidade_convidado = $1
+        "#,
+    )
+    .bind(origem)
+    .bind(destino)
+    .execute(&mut *tx)
+    .await?;
+
+    // Notas de avaliação também; se a avaliação já tiver nota para o destino, a da origem é descartada
+    sqlx::query(
+        r#"
+        UPDATE public.avaliacao_nota AS o
+        SET id_sub_habilidade = $2
+        WHERE o.id_sub_habilidade = $1
+          AND NOT EXISTS (
+              SELECT 1 FROM public.avaliacao_nota AS d
+              WHERE d.id_avaliacao = o.id_avaliacao AND d.id_sub_habilidade = $2
+          )
+        "#,
+    )
+    .bind(origem)
+    .bind(destino)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM public.avaliacao_nota WHERE id_sub_habilidade = $1")
+        .bind(origem)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM public.sub_habilidade WHERE id = $1")
+        .bind(origem)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    log::info!(
+        "Sub-habilidade {} mesclada em {} pelo admin {}",
+        origem,
+        destino,
+        admin.id
+    );
+    Ok(HttpResponse::Ok().json(resposta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{IF_MODIFIED_SINCE, IF_NONE_MATCH};
+    use actix_web::test::TestRequest;
+
+    fn instante(segundos: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(segundos)
+    }
+
+    fn data_http(segundos: u64) -> String {
+        HttpDate::from(instante(segundos)).to_string()
+    }
+
+    fn etag() -> EntityTag {
+        EntityTag::new_strong("abc".to_string())
+    }
+
+    #[test]
+    fn sem_condicoes_devolve_o_catalogo() {
+        let req = TestRequest::default().to_http_request();
+        assert!(!nao_modificado(&req, &etag(), instante(1_000)));
+    }
+
+    #[test]
+    fn etag_igual_ou_curinga_nao_mudou() {
+        for valor in ["\"abc\"", "W/\"abc\"", "\"xyz\", \"abc\"", "*"] {
+            let req = TestRequest::default()
+                .insert_header((IF_NONE_MATCH, valor))
+                .to_http_request();
+            assert!(nao_modificado(&req, &etag(), instante(1_000)), "{}", valor);
+        }
+    }
+
+    #[test]
+    fn etag_diferente_devolve_o_catalogo() {
+        let req = TestRequest::default()
+            .insert_header((IF_NONE_MATCH, "\"xyz\""))
+            .to_http_request();
+        assert!(!nao_modificado(&req, &etag(), instante(1_000)));
+    }
+
+    #[test]
+    fn if_modified_since_compara_com_last_modified() {
+        let req = TestRequest::default()
+            .insert_header((IF_MODIFIED_SINCE, data_http(1_000)))
+            .to_http_request();
+        assert!(nao_modificado(&req, &etag(), instante(1_000)));
+        assert!(nao_modificado(&req, &etag(), instante(999)));
+        assert!(!nao_modificado(&req, &etag(), instante(1_001)));
+    }
+
+    #[test]
+    fn etag_tem_precedencia_sobre_if_modified_since() {
+        // Data recente, mas o ETag mudou: o catálogo é devolvido
+        let req = TestRequest::default()
+            .insert_header((IF_NONE_MATCH, "\"xyz\""))
+            .insert_header((IF_MODIFIED_SINCE, data_http(2_000)))
+            .to_http_request();
+        assert!(!nao_modificado(&req, &etag(), instante(1_000)));
+
+        // Data antiga, mas o ETag confere: 304
+        let req = TestRequest::default()
+            .insert_header((IF_NONE_MATCH, "\"abc\""))
+            .insert_header((IF_MODIFIED_SINCE, data_http(500)))
+            .to_http_request();
+        assert!(nao_modificado(&req, &etag(), instante(1_000)));
+    }
+}
@@ -0,0 +1,1077 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use actix_web::http::header;
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::auth::{self, AuthUser, Jwt};
+use crate::bloqueios;
+use crate::error::{ApiError, ProblemDetails};
+use crate::paginacao::{self, Pagina, PaginaQuery};
+use crate::validacao::{self, JsonValidado};
+
+// Canal do LISTEN/NOTIFY usado para entregar mensagens entre instâncias da API
+const CANAL_NOTIFICACAO: &str = "swapp_chat";
+const ESPERA_RECONEXAO: Duration = Duration::from_secs(5);
+
+// Sem header Authorization, o cliente tem este prazo para mandar o token
+const PRAZO_AUTENTICACAO: Duration = Duration::from_secs(10);
+const INTERVALO_PING: Duration = Duration::from_secs(30);
+// Conexões sem nenhum frame (nem pong) por esse tempo são encerradas
+const TEMPO_MAXIMO_INATIVA: Duration = Duration::from_secs(75);
+const TAMANHO_MAXIMO_FRAME: usize = 16 * 1024;
+// Eventos "digitando" repetidos dentro deste intervalo são descartados
+const INTERVALO_DIGITANDO: Duration = Duration::from_secs(2);
+
+// Condição SQL: o par da conversa `c` tem match confirmado, em qualquer direção do like,
+// e nenhum dos dois bloqueou o outro
+fn par_com_match() -> String {
+    format!(
+        r#"(EXISTS (
+            SELECT 1 FROM public.teste_match tm
+            WHERE tm.match
+              AND ((tm.id_deu_like = c.id_usuario1 AND tm.id_liked = c.id_usuario2)
+                OR (tm.id_deu_like = c.id_usuario2 AND tm.id_liked = c.id_usuario1))
+        ) AND {})"#,
+        bloqueios::sem_bloqueio("c.id_usuario1", "c.id_usuario2")
+    )
+}
+
+// Mensagens do outro participante da conversa `c` depois do cursor de leitura do usuário $1
+pub const NAO_LIDAS: &str = r#"(
+    SELECT COUNT(*) FROM public.mensagem mn
+    WHERE mn.id_conversa = c.id
+      AND mn.id_remetente <> $1
+      AND mn.id > COALESCE((
+          SELECT l.id_ultima_lida FROM public.leitura_conversa l
+          WHERE l.id_conversa = c.id AND l.id_users = $1
+      ), 0)
+)"#;
+
+#[derive(Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Mensagem {
+    id: i64,
+    id_conversa: i32,
+    id_remetente: i32,
+    conteudo: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Conversa {
+    id: i32,
+    id_outro_usuario: i32,
+    // Sobrenome só aparece se o outro usuário o deixou público
+    nome_outro_usuario: String,
+    // Ausente enquanto ninguém escreveu
+    ultima_mensagem: Option<Mensagem>,
+    // Mensagens do outro usuário ainda não lidas
+    nao_lidas: i64,
+    // Mensagens com id até este valor já foram lidas pelo outro usuário (0: nenhuma)
+    id_ultima_lida_pelo_outro: i64,
+    created_at: NaiveDateTime,
+    // Momento da última mensagem (ou da criação da conversa)
+    atualizada_em: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow)]
+struct LinhaConversa {
+    id: i32,
+    id_outro_usuario: i32,
+    nome_outro_usuario: String,
+    nao_lidas: i64,
+    id_ultima_lida_pelo_outro: i64,
+    created_at: NaiveDateTime,
+    atualizada_em: NaiveDateTime,
+    ultima_id: Option<i64>,
+    ultima_id_remetente: Option<i32>,
+    ultima_conteudo: Option<String>,
+    ultima_created_at: Option<NaiveDateTime>,
+}
+
+impl From<LinhaConversa> for Conversa {
+    fn from(linha: LinhaConversa) -> Self {
+        let ultima_mensagem = match (
+            linha.ultima_id,
+            linha.ultima_id_remetente,
+            linha.ultima_conteudo,
+            linha.ultima_created_at,
+        ) {
+            (Some(id), Some(id_remetente), Some(conteudo), Some(created_at)) => Some(Mensagem {
+                id,
+                id_conversa: linha.id,
+                id_remetente,
+                conteudo,
+                created_at,
+            }),
+            _ => None,
+        };
+        Conversa {
+            id: linha.id,
+            id_outro_usuario: linha.id_outro_usuario,
+            nome_outro_usuario: linha.nome_outro_usuario,
+            ultima_mensagem,
+            nao_lidas: linha.nao_lidas,
+            id_ultima_lida_pelo_outro: linha.id_ultima_lida_pelo_outro,
+            created_at: linha.created_at,
+            atualizada_em: linha.atualizada_em,
+        }
+    }
+}
+
+// Conversas vistas pelo usuário $1; `filtro` completa o WHERE e pode usar $2 em diante
+fn select_conversa(filtro: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            c.id,
+            u.id AS id_outro_usuario,
+            CASE
+                WHEN u.sobrenome_publico THEN CONCAT(u.first_name, ' ', u.last_name)
+                ELSE u.first_name
+            END AS nome_outro_usuario,
+            {} AS nao_lidas,
+            COALESCE((
+                SELECT l.id_ultima_lida FROM public.leitura_conversa l
+                WHERE l.id_conversa = c.id AND l.id_users = u.id
+            ), 0) AS id_ultima_lida_pelo_outro,
+            c.created_at,
+            c.atualizada_em,
+            m.id AS ultima_id,
+            m.id_remetente AS ultima_id_remetente,
+            m.conteudo AS ultima_conteudo,
+            m.created_at AS ultima_created_at
+        FROM public.conversa c
+        JOIN public.users u
+            ON u.id = CASE WHEN c.id_usuario1 = $1 THEN c.id_usuario2 ELSE c.id_usuario1 END
+        LEFT JOIN LATERAL (
+            SELECT id, id_remetente, conteudo, created_at
+            FROM public.mensagem
+            WHERE id_conversa = c.id
+            ORDER BY id DESC
+            LIMIT 1
+        ) m ON TRUE
+        WHERE (c.id_usuario1 = $1 OR c.id_usuario2 = $1) AND {}
+        "#,
+        NAO_LIDAS, filtro
+    )
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validar_abrir_conversa"))]
+pub struct AbrirConversaRequest {
+    // Usuário autenticado
+    #[validate(range(min = 1, message = "ID de usuário inválido"))]
+    id_users: i32,
+    #[validate(range(min = 1, message = "ID de usuário inválido"))]
+    id_outro: i32,
+}
+
+fn validar_abrir_conversa(dados: &AbrirConversaRequest) -> Result<(), ValidationError> {
+    if dados.id_users == dados.id_outro {
+        return Err(validacao::erro_no_campo(
+            "id_outro",
+            "self_reference",
+            "O usuário não pode conversar consigo mesmo",
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct LeituraRequest {
+    // Última mensagem vista; leituras nunca retrocedem
+    #[validate(range(min = 1, message = "ID de mensagem inválido"))]
+    id_mensagem: i64,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Leitura {
+    id_conversa: i32,
+    id_users: i32,
+    id_ultima_lida: i64,
+    lida_em: NaiveDateTime,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ContagemNaoLidas {
+    // Mensagens não lidas somando todas as conversas
+    total: i64,
+    // Conversas com ao menos uma mensagem não lida
+    conversas: i64,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct NovaMensagem {
+    #[validate(
+        length(min = 1, max = 2000, message = "A mensagem deve ter entre 1 e 2000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    conteudo: String,
+}
+
+// Frames enviados pelo cliente no WebSocket, em JSON
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum ComandoChat {
+    // Obrigatório como primeiro frame quando a conexão não tem header Authorization
+    Autenticar { token: String },
+    Mensagem { id_conversa: i32, conteudo: String },
+    // Marca como lidas as mensagens até id_mensagem
+    Leitura { id_conversa: i32, id_mensagem: i64 },
+    // Enviado periodicamente enquanto o usuário escreve
+    Digitando { id_conversa: i32 },
+}
+
+// Frames enviados pelo servidor no WebSocket, em JSON
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum EventoChat {
+    Autenticado { id_users: i32 },
+    // Mensagem nova numa conversa do usuário, inclusive as que ele próprio enviou
+    Mensagem { mensagem: Mensagem },
+    // Um participante avançou o cursor de leitura (recibo de leitura)
+    Leitura { id_conversa: i32, id_users: i32, id_ultima_lida: i64 },
+    // O outro participante está escrevendo; some se não for repetido em alguns segundos
+    Digitando { id_conversa: i32, id_users: i32 },
+    Erro { erro: ProblemDetails },
+}
+
+impl From<ApiError> for EventoChat {
+    fn from(e: ApiError) -> Self {
+        EventoChat::Erro { erro: e.problem() }
+    }
+}
+
+// Notificações publicadas no Postgres para as instâncias da API. Mensagens levam
+// só o id: o payload do NOTIFY é limitado a 8000 bytes
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+enum Notificacao {
+    Mensagem { id: i64, destinatarios: [i32; 2] },
+    Leitura { id_conversa: i32, id_users: i32, id_ultima_lida: i64, destinatarios: [i32; 2] },
+    Digitando { id_conversa: i32, id_users: i32, destinatario: i32 },
+}
+
+// Publica a notificação; dentro de uma transação, só é entregue após o commit
+async fn notificar(
+    executor: impl sqlx::PgExecutor<'_>,
+    notificacao: &Notificacao,
+) -> Result<(), ApiError> {
+    let payload = serde_json::to_string(notificacao).map_err(|e| {
+        log::error!("Erro ao serializar notificação do chat: {:?}", e);
+        ApiError::Internal
+    })?;
+
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(CANAL_NOTIFICACAO)
+        .bind(payload)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+// Participantes da conversa, se o usuário puder acessá-la agora
+async fn acessar_conversa(
+    executor: impl sqlx::PgExecutor<'_>,
+    id_conversa: i32,
+    usuario: &AuthUser,
+) -> Result<[i32; 2], ApiError> {
+    let query = format!(
+        "SELECT c.id_usuario1, c.id_usuario2, {} AS com_match FROM public.conversa c WHERE c.id = $1",
+        par_com_match()
+    );
+
+    let (id_usuario1, id_usuario2, com_match) = sqlx::query_as::<_, (i32, i32, bool)>(&query)
+        .bind(id_conversa)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Conversa não encontrada".to_string()))?;
+
+    usuario.garantir_participante(id_usuario1, id_usuario2)?;
+    if !com_match {
+        return Err(ApiError::Forbidden(
+            "O chat só está disponível enquanto houver match entre os usuários".to_string(),
+        ));
+    }
+    Ok([id_usuario1, id_usuario2])
+}
+
+// Grava a mensagem e avisa as instâncias da API. Quem envia leu a conversa até ali
+async fn gravar_mensagem(
+    pool: &PgPool,
+    id_conversa: i32,
+    usuario: &AuthUser,
+    conteudo: &str,
+) -> Result<Mensagem, ApiError> {
+    let mut tx = pool.begin().await?;
+    let destinatarios = acessar_conversa(&mut *tx, id_conversa, usuario).await?;
+
+    let query = r#"
+        INSERT INTO public.mensagem (id_conversa, id_remetente, conteudo)
+        VALUES ($1, $2, $3)
+        RETURNING id, id_conversa, id_remetente, conteudo, created_at
+    "#;
+
+    let mensagem = sqlx::query_as::<_, Mensagem>(query)
+        .bind(id_conversa)
+        .bind(usuario.id)
+        .bind(conteudo)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE public.conversa SET atualizada_em = $2 WHERE id = $1")
+        .bind(id_conversa)
+        .bind(mensagem.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+    avancar_leitura(&mut *tx, id_conversa, usuario.id, mensagem.id).await?;
+
+    let notificacao = Notificacao::Mensagem {
+        id: mensagem.id,
+        destinatarios,
+    };
+    notificar(&mut *tx, &notificacao).await?;
+
+    tx.commit().await?;
+    Ok(mensagem)
+}
+
+// Leitura atualizada, ou None se o cursor já estava nessa mensagem ou além
+async fn avancar_leitura(
+    executor: impl sqlx::PgExecutor<'_>,
+    id_conversa: i32,
+    id_users: i32,
+    id_mensagem: i64,
+) -> Result<Option<Leitura>, ApiError> {
+    let query = r#"
+        INSERT INTO public.leitura_conversa (id_conversa, id_users, id_ultima_lida)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (id_conversa, id_users) DO UPDATE
+        SET id_ultima_lida = EXCLUDED.id_ultima_lida, lida_em = now()
+        WHERE leitura_conversa.id_ultima_lida < EXCLUDED.id_ultima_lida
+        RETURNING id_conversa, id_users, id_ultima_lida, lida_em
+    "#;
+
+    let leitura = sqlx::query_as::<_, Leitura>(query)
+        .bind(id_conversa)
+        .bind(id_users)
+        .bind(id_mensagem)
+        .fetch_optional(executor)
+        .await?;
+    Ok(leitura)
+}
+
+async fn marcar_lida(
+    pool: &PgPool,
+    id_conversa: i32,
+    usuario: &AuthUser,
+    id_mensagem: i64,
+) -> Result<Leitura, ApiError> {
+    let mut tx = pool.begin().await?;
+    let destinatarios = acessar_conversa(&mut *tx, id_conversa, usuario).await?;
+
+    let existe = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM public.mensagem WHERE id = $1 AND id_conversa = $2)",
+    )
+    .bind(id_mensagem)
+    .bind(id_conversa)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !existe {
+        return Err(ApiError::InvalidReference(
+            "Mensagem não encontrada nesta conversa".to_string(),
+        ));
+    }
+
+    let leitura = match avancar_leitura(&mut *tx, id_conversa, usuario.id, id_mensagem).await? {
+        Some(leitura) => {
+            let notificacao = Notificacao::Leitura {
+                id_conversa,
+                id_users: usuario.id,
+                id_ultima_lida: leitura.id_ultima_lida,
+                destinatarios,
+            };
+            notificar(&mut *tx, &notificacao).await?;
+            leitura
+        }
+        // Cursor já adiante: devolve o atual sem avisar ninguém
+        None => {
+            sqlx::query_as::<_, Leitura>(
+                r#"
+                SELECT id_conversa, id_users, id_ultima_lida, lida_em
+                FROM public.leitura_conversa
+                WHERE id_conversa = $1 AND id_users = $2
+                "#,
+            )
+            .bind(id_conversa)
+            .bind(usuario.id)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+
+    tx.commit().await?;
+    Ok(leitura)
+}
+
+async fn avisar_digitando(
+    pool: &PgPool,
+    id_conversa: i32,
+    usuario: &AuthUser,
+) -> Result<(), ApiError> {
+    let [id_usuario1, id_usuario2] = acessar_conversa(pool, id_conversa, usuario).await?;
+    let destinatario = if id_usuario1 == usuario.id {
+        id_usuario2
+    } else {
+        id_usuario1
+    };
+    notificar(
+        pool,
+        &Notificacao::Digitando {
+            id_conversa,
+            id_users: usuario.id,
+            destinatario,
+        },
+    )
+    .await
+}
+
+// POST: Abre (ou retorna) a conversa entre dois usuários com match
+#[utoipa::path(
+    post,
+    path = "/conversas",
+    request_body = AbrirConversaRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Conversa já existente", body = Conversa),
+        (status = 201, description = "Conversa criada", body = Conversa),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário ou usuários sem match", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao abrir conversa", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn abrir_conversa(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    dados: JsonValidado<AbrirConversaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.garantir_dono(dados.id_users)?;
+    let (id_usuario1, id_usuario2) = if dados.id_users < dados.id_outro {
+        (dados.id_users, dados.id_outro)
+    } else {
+        (dados.id_outro, dados.id_users)
+    };
+
+    let query = r#"
+        INSERT INTO public.conversa (id_usuario1, id_usuario2)
+        SELECT $1, $2
+        WHERE EXISTS (
+            SELECT 1 FROM public.teste_match tm
+            WHERE tm.match
+              AND ((tm.id_deu_like = $1 AND tm.id_liked = $2)
+                OR (tm.id_deu_like = $2 AND tm.id_liked = $1))
+        )
+        ON CONFLICT (id_usuario1, id_usuario2) DO NOTHING
+        RETURNING id
+    "#;
+
+    let criada = sqlx::query_scalar::<_, i32>(query)
+        .bind(id_usuario1)
+        .bind(id_usuario2)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .is_some();
+
+    let query = select_conversa(&format!(
+        "c.id_usuario1 = $2 AND c.id_usuario2 = $3 AND {}",
+        par_com_match()
+    ));
+
+    let conversa = sqlx::query_as::<_, LinhaConversa>(&query)
+        .bind(auth.id)
+        .bind(id_usuario1)
+        .bind(id_usuario2)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .map(Conversa::from)
+        .ok_or_else(|| {
+            ApiError::Forbidden("Só é possível conversar com usuários com quem há match".to_string())
+        })?;
+
+    if criada {
+        Ok(HttpResponse::Created().json(conversa))
+    } else {
+        Ok(HttpResponse::Ok().json(conversa))
+    }
+}
+
+// GET: Conversas do usuário com matches ativos, da atividade mais recente para a mais antiga
+#[utoipa::path(
+    get,
+    path = "/conversas/usuario/{id_users}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário"),
+        PaginaQuery
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Conversas com a última mensagem de cada uma", body = PaginaConversas),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar conversas", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn listar_conversas(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    params: web::Query<PaginaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+    validacao::validar(&*params)?;
+
+    let after = paginacao::decodificar::<(NaiveDateTime, i32)>(params.after.as_deref())?;
+
+    let query = format!(
+        "{} ORDER BY c.atualizada_em DESC, c.id DESC LIMIT $4",
+        select_conversa(&format!(
+            "{} AND ($2::timestamp IS NULL OR (c.atualizada_em, c.id) < ($2, $3))",
+            par_com_match()
+        ))
+    );
+
+    let conversas = sqlx::query_as::<_, LinhaConversa>(&query)
+        .bind(id_users)
+        .bind(after.map(|(atualizada_em, _)| atualizada_em))
+        .bind(after.map(|(_, id)| id))
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?
+        .into_iter()
+        .map(Conversa::from)
+        .collect();
+
+    let pagina = Pagina::montar(conversas, params.limit, |c: &Conversa| (c.atualizada_em, c.id))?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
+
+// GET: Histórico de mensagens de uma conversa, das mais recentes para as mais antigas
+#[utoipa::path(
+    get,
+    path = "/conversas/{id}/mensagens",
+    params(
+        ("id" = i32, Path, description = "ID da conversa"),
+        PaginaQuery
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Mensagens da conversa", body = PaginaMensagens),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da conversa ou o match foi desfeito", body = ProblemDetails),
+        (status = 404, description = "Conversa não encontrada", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar mensagens", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_mensagens(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    params: web::Query<PaginaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id_conversa = path.into_inner();
+    validacao::validar(&*params)?;
+    let after = paginacao::decodificar::<i64>(params.after.as_deref())?;
+
+    acessar_conversa(pool.get_ref(), id_conversa, &auth).await?;
+
+    let query = r#"
+        SELECT id, id_conversa, id_remetente, conteudo, created_at
+        FROM public.mensagem
+        WHERE id_conversa = $1 AND ($2::int8 IS NULL OR id < $2)
+        ORDER BY id DESC
+        LIMIT $3
+    "#;
+
+    let mensagens = sqlx::query_as::<_, Mensagem>(query)
+        .bind(id_conversa)
+        .bind(after)
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(mensagens, params.limit, |m| m.id)?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
+
+// POST: Envia uma mensagem; também é entregue em tempo real pelo WebSocket
+#[utoipa::path(
+    post,
+    path = "/conversas/{id}/mensagens",
+    params(
+        ("id" = i32, Path, description = "ID da conversa")
+    ),
+    request_body = NovaMensagem,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Mensagem enviada", body = Mensagem),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da conversa ou o match foi desfeito", body = ProblemDetails),
+        (status = 404, description = "Conversa não encontrada", body = ProblemDetails),
+        (status = 422, description = "Mensagem vazia ou longa demais", body = ProblemDetails),
+        (status = 500, description = "Erro ao enviar mensagem", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn enviar_mensagem(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    dados: JsonValidado<NovaMensagem>,
+) -> Result<HttpResponse, ApiError> {
+    let mensagem = gravar_mensagem(pool.get_ref(), path.into_inner(), &auth, &dados.conteudo).await?;
+    Ok(HttpResponse::Created().json(mensagem))
+}
+
+// PUT: Marca como lidas as mensagens da conversa até a informada
+#[utoipa::path(
+    put,
+    path = "/conversas/{id}/leitura",
+    params(
+        ("id" = i32, Path, description = "ID da conversa")
+    ),
+    request_body = LeituraRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Cursor de leitura do usuário; não retrocede se a mensagem for anterior a ele", body = Leitura),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da conversa ou o match foi desfeito", body = ProblemDetails),
+        (status = 404, description = "Conversa não encontrada", body = ProblemDetails),
+        (status = 422, description = "Mensagem inexistente ou de outra conversa", body = ProblemDetails),
+        (status = 500, description = "Erro ao registrar leitura", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn registrar_leitura(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    dados: JsonValidado<LeituraRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let leitura = marcar_lida(pool.get_ref(), path.into_inner(), &auth, dados.id_mensagem).await?;
+    Ok(HttpResponse::Ok().json(leitura))
+}
+
+// GET: Total de mensagens não lidas do usuário, para exibir como badge
+#[utoipa::path(
+    get,
+    path = "/conversas/usuario/{id_users}/nao_lidas",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Não lidas nas conversas com matches ativos", body = ContagemNaoLidas),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 500, description = "Erro ao contar mensagens", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn contar_nao_lidas(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    let query = format!(
+        r#"
+        SELECT COALESCE(SUM(n), 0)::int8 AS total, COUNT(*) FILTER (WHERE n > 0) AS conversas
+        FROM (
+            SELECT {} AS n
+            FROM public.conversa c
+            WHERE (c.id_usuario1 = $1 OR c.id_usuario2 = $1) AND {}
+        ) contagens
+        "#,
+        NAO_LIDAS, par_com_match()
+    );
+
+    let contagem = sqlx::query_as::<_, ContagemNaoLidas>(&query)
+        .bind(id_users)
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(contagem))
+}
+
+type Conexoes = HashMap<i32, Vec<(u64, mpsc::UnboundedSender<String>)>>;
+
+// Conexões WebSocket abertas nesta instância, por usuário. Cada conexão recebe
+// os eventos já serializados por um canal próprio
+#[derive(Default)]
+pub struct Hub {
+    conexoes: Mutex<Conexoes>,
+    proxima_conexao: AtomicU64,
+}
+
+impl Hub {
+    fn registrar(&self, id_users: i32) -> (u64, mpsc::UnboundedReceiver<String>) {
+        let id_conexao = self.proxima_conexao.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.travar().entry(id_users).or_default().push((id_conexao, tx));
+        (id_conexao, rx)
+    }
+
+    fn remover(&self, id_users: i32, id_conexao: u64) {
+        let mut conexoes = self.travar();
+        if let Some(lista) = conexoes.get_mut(&id_users) {
+            lista.retain(|(id, _)| *id != id_conexao);
+            if lista.is_empty() {
+                conexoes.remove(&id_users);
+            }
+        }
+    }
+
+    fn conectado(&self, id_users: i32) -> bool {
+        self.travar().contains_key(&id_users)
+    }
+
+    fn entregar(&self, destinatarios: &[i32], evento: &EventoChat) {
+        let texto = match serde_json::to_string(evento) {
+            Ok(texto) => texto,
+            Err(e) => {
+                log::error!("Erro ao serializar evento do chat: {:?}", e);
+                return;
+            }
+        };
+        let conexoes = self.travar();
+        for id_users in destinatarios {
+            for (_, tx) in conexoes.get(id_users).into_iter().flatten() {
+                // Falha só se a sessão já terminou; ela se remove ao encerrar
+                let _ = tx.send(texto.clone());
+            }
+        }
+    }
+
+    fn travar(&self) -> MutexGuard<'_, Conexoes> {
+        self.conexoes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Tarefa de fundo: recebe as notificações do Postgres e entrega às conexões desta instância.
+// Mensagens gravadas enquanto a escuta está caída não são reenviadas; o cliente as busca pela API
+pub async fn escutar(pool: PgPool, hub: Arc<Hub>) {
+    loop {
+        if let Err(e) = ouvir_notificacoes(&pool, &hub).await {
+            log::error!("Escuta de notificações do chat interrompida: {:?}", e);
+        }
+        tokio::time::sleep(ESPERA_RECONEXAO).await;
+    }
+}
+
+async fn ouvir_notificacoes(pool: &PgPool, hub: &Hub) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CANAL_NOTIFICACAO).await?;
+    loop {
+        let notificacao = listener.recv().await?;
+        match serde_json::from_str::<Notificacao>(notificacao.payload()) {
+            Ok(notificacao) => despachar(pool, hub, notificacao).await,
+            Err(e) => log::warn!("Notificação do chat ignorada: {:?}", e),
+        }
+    }
+}
+
+async fn despachar(pool: &PgPool, hub: &Hub, notificacao: Notificacao) {
+    match notificacao {
+        Notificacao::Mensagem { id, destinatarios } => {
+            if !destinatarios.iter().any(|id_users| hub.conectado(*id_users)) {
+                return;
+            }
+            let query = r#"
+                SELECT id, id_conversa, id_remetente, conteudo, created_at
+                FROM public.mensagem
+                WHERE id = $1
+            "#;
+            match sqlx::query_as::<_, Mensagem>(query)
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+            {
+                Ok(Some(mensagem)) => hub.entregar(&destinatarios, &EventoChat::Mensagem { mensagem }),
+                Ok(None) => {}
+                Err(e) => log::error!("Erro ao buscar mensagem {} para entrega: {:?}", id, e),
+            }
+        }
+        Notificacao::Leitura {
+            id_conversa,
+            id_users,
+            id_ultima_lida,
+            destinatarios,
+        } => hub.entregar(
+            &destinatarios,
+            &EventoChat::Leitura {
+                id_conversa,
+                id_users,
+                id_ultima_lida,
+            },
+        ),
+        Notificacao::Digitando {
+            id_conversa,
+            id_users,
+            destinatario,
+        } => hub.entregar(
+            &[destinatario],
+            &EventoChat::Digitando {
+                id_conversa,
+                id_users,
+            },
+        ),
+    }
+}
+
+// GET: Abre o WebSocket do chat. O token vai no header Authorization ou, para clientes
+// que não conseguem enviá-lo (navegadores), no primeiro frame: {"tipo":"autenticar","token":"..."}
+#[utoipa::path(
+    get,
+    path = "/ws/chat",
+    security((), ("bearer_auth" = [])),
+    responses(
+        (status = 101, description = "Conexão estabelecida. Frames do cliente: ComandoChat; frames do servidor: EventoChat"),
+        (status = 400, description = "Requisição sem upgrade para WebSocket", body = ProblemDetails),
+        (status = 401, description = "Token inválido ou expirado no header Authorization", body = ProblemDetails)
+    )
+)]
+pub async fn conectar(
+    req: HttpRequest,
+    corpo: web::Payload,
+    pool: web::Data<PgPool>,
+    hub: web::Data<Hub>,
+    jwt: web::Data<Jwt>,
+) -> Result<HttpResponse, ApiError> {
+    let usuario = if req.headers().contains_key(header::AUTHORIZATION) {
+        Some(auth::autenticar(&req)?)
+    } else {
+        None
+    };
+
+    let (resposta, sessao, stream) = actix_ws::handle(&req, corpo)
+        .map_err(|_| ApiError::BadRequest("Requisição sem upgrade para WebSocket".to_string()))?;
+
+    rt::spawn(sessao_chat(
+        pool.into_inner(),
+        hub.into_inner(),
+        jwt.into_inner(),
+        sessao,
+        stream.max_frame_size(TAMANHO_MAXIMO_FRAME),
+        usuario,
+    ));
+
+    Ok(resposta)
+}
+
+async fn sessao_chat(
+    pool: Arc<PgPool>,
+    hub: Arc<Hub>,
+    jwt: Arc<Jwt>,
+    mut sessao: Session,
+    mut stream: MessageStream,
+    usuario: Option<AuthUser>,
+) {
+    let usuario = match usuario {
+        Some(usuario) => usuario,
+        None => match aguardar_autenticacao(&jwt, &mut sessao, &mut stream).await {
+            Some(usuario) => usuario,
+            None => {
+                let _ = sessao.close(Some(CloseCode::Policy.into())).await;
+                return;
+            }
+        },
+    };
+
+    let (id_conexao, mut eventos) = hub.registrar(usuario.id);
+    let mut ping = tokio::time::interval(INTERVALO_PING);
+    let mut ultima_atividade = Instant::now();
+    // Último "digitando" repassado por conversa, para descartar repetições
+    let mut digitando: HashMap<i32, Instant> = HashMap::new();
+
+    let motivo = if enviar_evento(&mut sessao, &EventoChat::Autenticado { id_users: usuario.id })
+        .await
+    {
+        loop {
+            tokio::select! {
+                frame = stream.recv() => {
+                    ultima_atividade = Instant::now();
+                    let resposta = match frame {
+                        Some(Ok(Message::Text(texto))) => {
+                            tratar_comando(&pool, &usuario, &mut digitando, &texto).await
+                        }
+                        Some(Ok(Message::Binary(_))) => Some(EventoChat::from(ApiError::BadRequest(
+                            "Envie os comandos como texto JSON".to_string(),
+                        ))),
+                        Some(Ok(Message::Ping(bytes))) => {
+                            if sessao.pong(&bytes).await.is_err() {
+                                break None;
+                            }
+                            None
+                        }
+                        Some(Ok(Message::Close(motivo))) => break motivo,
+                        Some(Ok(_)) => None,
+                        Some(Err(e)) => {
+                            log::debug!("Erro de protocolo no WebSocket do chat: {:?}", e);
+                            break Some(CloseCode::Protocol.into());
+                        }
+                        None => break None,
+                    };
+                    if let Some(evento) = resposta {
+                        if !enviar_evento(&mut sessao, &evento).await {
+                            break None;
+                        }
+                    }
+                }
+                Some(evento) = eventos.recv() => {
+                    if sessao.text(evento).await.is_err() {
+                        break None;
+                    }
+                }
+                _ = ping.tick() => {
+                    if ultima_atividade.elapsed() > TEMPO_MAXIMO_INATIVA {
+                        break Some(CloseReason::from((CloseCode::Away, "Conexão inativa")));
+                    }
+                    if sessao.ping(b"").await.is_err() {
+                        break None;
+                    }
+                }
+            }
+        }
+    } else {
+        None
+    };
+
+    hub.remover(usuario.id, id_conexao);
+    let _ = sessao.close(motivo).await;
+}
+
+async fn aguardar_autenticacao(
+    jwt: &Jwt,
+    sessao: &mut Session,
+    stream: &mut MessageStream,
+) -> Option<AuthUser> {
+    let primeiro = tokio::time::timeout(PRAZO_AUTENTICACAO, async {
+        loop {
+            match stream.recv().await {
+                Some(Ok(Message::Text(texto))) => return Some(texto),
+                Some(Ok(Message::Ping(bytes))) => {
+                    let _ = sessao.pong(&bytes).await;
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return None,
+                Some(Ok(_)) => {}
+            }
+        }
+    })
+    .await
+    .ok()
+    .flatten()?;
+
+    let resultado = match serde_json::from_str::<ComandoChat>(&primeiro) {
+        Ok(ComandoChat::Autenticar { token }) => jwt.autenticar(&token),
+        _ => Err(ApiError::Unauthorized(
+            "O primeiro frame deve ser {\"tipo\":\"autenticar\",\"token\":\"<access token>\"}"
+                .to_string(),
+        )),
+    };
+
+    match resultado {
+        Ok(usuario) => Some(usuario),
+        Err(e) => {
+            enviar_evento(sessao, &EventoChat::from(e)).await;
+            None
+        }
+    }
+}
+
+// Erros voltam só para quem enviou; mensagens gravadas chegam a todos pelo Hub
+async fn tratar_comando(
+    pool: &PgPool,
+    usuario: &AuthUser,
+    digitando: &mut HashMap<i32, Instant>,
+    texto: &str,
+) -> Option<EventoChat> {
+    let comando = match serde_json::from_str::<ComandoChat>(texto) {
+        Ok(comando) => comando,
+        Err(e) => return Some(ApiError::BadRequest(format!("Comando inválido: {}", e)).into()),
+    };
+
+    match comando {
+        ComandoChat::Autenticar { .. } => {
+            Some(ApiError::Conflict("Conexão já autenticada".to_string()).into())
+        }
+        ComandoChat::Mensagem {
+            id_conversa,
+            conteudo,
+        } => {
+            let dados = NovaMensagem { conteudo };
+            let resultado = match validacao::validar(&dados) {
+                Ok(()) => gravar_mensagem(pool, id_conversa, usuario, &dados.conteudo).await,
+                Err(e) => Err(e),
+            };
+            resultado.err().map(EventoChat::from)
+        }
+        ComandoChat::Leitura {
+            id_conversa,
+            id_mensagem,
+        } => marcar_lida(pool, id_conversa, usuario, id_mensagem)
+            .await
+            .err()
+            .map(EventoChat::from),
+        ComandoChat::Digitando { id_conversa } => {
+            let agora = Instant::now();
+            if digitando
+                .get(&id_conversa)
+                .is_some_and(|anterior| agora.duration_since(*anterior) < INTERVALO_DIGITANDO)
+            {
+                return None;
+            }
+            digitando.insert(id_conversa, agora);
+            avisar_digitando(pool, id_conversa, usuario)
+                .await
+                .err()
+                .map(EventoChat::from)
+        }
+    }
+}
+
+// Retorna false se a conexão já foi fechada
+async fn enviar_evento(sessao: &mut Session, evento: &EventoChat) -> bool {
+    match serde_json::to_string(evento) {
+        Ok(texto) => sessao.text(texto).await.is_ok(),
+        Err(e) => {
+            log::error!("Erro ao serializar evento do chat: {:?}", e);
+            true
+        }
+    }
+}
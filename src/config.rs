@@ -0,0 +1,445 @@
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+// Caminho padrão do arquivo TOML opcional (pode ser trocado via SWAPP_CONFIG)
+const ARQUIVO_PADRAO: &str = "config.toml";
+
+const NIVEIS_LOG: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("não foi possível ler o arquivo de configuração {0}: {1}")]
+    Arquivo(PathBuf, std::io::Error),
+    #[error("arquivo de configuração {0} inválido: {1}")]
+    Toml(PathBuf, toml::de::Error),
+    #[error("variável de ambiente {var} inválida: {mensagem}")]
+    Ambiente { var: &'static str, mensagem: String },
+    #[error("configuração inválida: {}", .0.join("; "))]
+    Validacao(Vec<String>),
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub run_migrations: bool,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            max_connections: 5,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: 600,
+            run_migrations: false,
+        }
+    }
+}
+
+impl std::fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseConfig")
+            .field("url", &url_sem_senha(&self.url))
+            .field("max_connections", &self.max_connections)
+            .field("min_connections", &self.min_connections)
+            .field("acquire_timeout_secs", &self.acquire_timeout_secs)
+            .field("idle_timeout_secs", &self.idle_timeout_secs)
+            .field("run_migrations", &self.run_migrations)
+            .finish()
+    }
+}
+
+// URL de conexão com a senha trocada por ***; se não der para interpretá-la, nada é exibido
+fn url_sem_senha(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut url) => {
+            if url.password().is_some() {
+                let _ = url.set_password(Some("***"));
+            }
+            url.to_string()
+        }
+        Err(_) if url.is_empty() => String::new(),
+        Err(_) => "***".to_string(),
+    }
+}
+
+impl DatabaseConfig {
+    pub fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(self.acquire_timeout_secs)
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    pub workers: Option<usize>,
+    pub cors_origins: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: "0.0.0.0:8080".to_string(),
+            workers: None,
+            cors_origins: vec!["*".to_string()],
+        }
+    }
+}
+
+impl ServerConfig {
+    // "*" libera qualquer origem, como era antes da configuração existir
+    pub fn allow_any_origin(&self) -> bool {
+        self.cors_origins.iter().any(|o| o == "*")
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    pub access_token_ttl_secs: u64,
+    pub refresh_token_ttl_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: String::new(),
+            access_token_ttl_secs: 15 * 60,
+            refresh_token_ttl_secs: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+// Não imprime o segredo em logs de depuração
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("jwt_secret", &"***")
+            .field("access_token_ttl_secs", &self.access_token_ttl_secs)
+            .field("refresh_token_ttl_secs", &self.refresh_token_ttl_secs)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendMidia {
+    Local,
+    S3,
+}
+
+impl FromStr for BackendMidia {
+    type Err = String;
+
+    fn from_str(valor: &str) -> Result<Self, Self::Err> {
+        match valor.to_ascii_lowercase().as_str() {
+            "local" => Ok(BackendMidia::Local),
+            "s3" => Ok(BackendMidia::S3),
+            _ => Err("esperado local ou s3".to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct S3Config {
+    // Ex.: https://s3.sa-east-1.amazonaws.com ou http://localhost:9000 (MinIO)
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    // Base das URLs devolvidas aos clientes; vazio usa {endpoint}/{bucket}
+    pub url_publica: String,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        S3Config {
+            endpoint: String::new(),
+            bucket: String::new(),
+            region: "us-east-1".to_string(),
+            access_key: String::new(),
+            secret_key: String::new(),
+            url_publica: String::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("bucket", &self.bucket)
+            .field("region", &self.region)
+            .field("access_key", &self.access_key)
+            .field("secret_key", &"***")
+            .field("url_publica", &self.url_publica)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiaConfig {
+    pub backend: BackendMidia,
+    // Pasta dos arquivos no backend local, servida em /midia
+    pub diretorio: PathBuf,
+    pub tamanho_maximo_bytes: usize,
+    pub s3: S3Config,
+}
+
+impl Default for MidiaConfig {
+    fn default() -> Self {
+        MidiaConfig {
+            backend: BackendMidia::Local,
+            diretorio: PathBuf::from("midia"),
+            tamanho_maximo_bytes: 5 * 1024 * 1024,
+            s3: S3Config::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub server: ServerConfig,
+    pub auth: AuthConfig,
+    pub midia: MidiaConfig,
+    pub log_level: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            database: DatabaseConfig::default(),
+            server: ServerConfig::default(),
+            auth: AuthConfig::default(),
+            midia: MidiaConfig::default(),
+            log_level: "info".to_string(),
+        }
+    }
+}
+
+impl Config {
+    // Ordem de precedência: valores padrão < arquivo TOML < .env / variáveis de ambiente
+    pub fn load() -> Result<Config, ConfigError> {
+        dotenv::dotenv().ok();
+
+        let mut config = match std::env::var("SWAPP_CONFIG") {
+            Ok(caminho) => Config::from_file(Path::new(&caminho))?,
+            Err(_) if Path::new(ARQUIVO_PADRAO).exists() => {
+                Config::from_file(Path::new(ARQUIVO_PADRAO))?
+            }
+            Err(_) => Config::default(),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(caminho: &Path) -> Result<Config, ConfigError> {
+        let conteudo = std::fs::read_to_string(caminho)
+            .map_err(|e| ConfigError::Arquivo(caminho.to_path_buf(), e))?;
+        toml::from_str(&conteudo).map_err(|e| ConfigError::Toml(caminho.to_path_buf(), e))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        if let Some(url) = env_string("DATABASE_URL") {
+            self.database.url = url;
+        }
+        if let Some(valor) = env_parse("DATABASE_MAX_CONNECTIONS")? {
+            self.database.max_connections = valor;
+        }
+        if let Some(valor) = env_parse("DATABASE_MIN_CONNECTIONS")? {
+            self.database.min_connections = valor;
+        }
+        if let Some(valor) = env_parse("DATABASE_ACQUIRE_TIMEOUT_SECS")? {
+            self.database.acquire_timeout_secs = valor;
+        }
+        if let Some(valor) = env_parse("DATABASE_IDLE_TIMEOUT_SECS")? {
+            self.database.idle_timeout_secs = valor;
+        }
+        if let Some(valor) = env_parse("DATABASE_RUN_MIGRATIONS")? {
+            self.database.run_migrations = valor;
+        }
+        if let Some(endereco) = env_string("BIND_ADDRESS") {
+            self.server.bind_address = endereco;
+        }
+        if let Some(valor) = env_parse("WORKERS")? {
+            self.server.workers = Some(valor);
+        }
+        if let Some(origens) = env_string("CORS_ORIGINS") {
+            self.server.cors_origins = origens
+                .split(',')
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty())
+                .collect();
+        }
+        if let Some(segredo) = env_string("JWT_SECRET") {
+            self.auth.jwt_secret = segredo;
+        }
+        if let Some(valor) = env_parse("JWT_ACCESS_TTL_SECS")? {
+            self.auth.access_token_ttl_secs = valor;
+        }
+        if let Some(valor) = env_parse("JWT_REFRESH_TTL_SECS")? {
+            self.auth.refresh_token_ttl_secs = valor;
+        }
+        if let Some(valor) = env_parse("MIDIA_BACKEND")? {
+            self.midia.backend = valor;
+        }
+        if let Some(diretorio) = env_string("MIDIA_DIRETORIO") {
+            self.midia.diretorio = PathBuf::from(diretorio);
+        }
+        if let Some(valor) = env_parse("MIDIA_TAMANHO_MAXIMO_BYTES")? {
+            self.midia.tamanho_maximo_bytes = valor;
+        }
+        if let Some(endpoint) = env_string("S3_ENDPOINT") {
+            self.midia.s3.endpoint = endpoint;
+        }
+        if let Some(bucket) = env_string("S3_BUCKET") {
+            self.midia.s3.bucket = bucket;
+        }
+        if let Some(regiao) = env_string("S3_REGION") {
+            self.midia.s3.region = regiao;
+        }
+        if let Some(chave) = env_string("S3_ACCESS_KEY") {
+            self.midia.s3.access_key = chave;
+        }
+        if let Some(segredo) = env_string("S3_SECRET_KEY") {
+            self.midia.s3.secret_key = segredo;
+        }
+        if let Some(url) = env_string("S3_URL_PUBLICA") {
+            self.midia.s3.url_publica = url;
+        }
+        if let Some(nivel) = env_string("LOG_LEVEL") {
+            self.log_level = nivel;
+        }
+        Ok(())
+    }
+
+    // Junta todos os problemas encontrados para que o deploy falhe com uma mensagem completa
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut erros = Vec::new();
+
+        if self.database.url.is_empty() {
+            erros.push("DATABASE_URL é obrigatória".to_string());
+        } else if !self.database.url.starts_with("postgres://")
+            && !self.database.url.starts_with("postgresql://")
+        {
+            erros.push("DATABASE_URL deve começar com postgres:// ou postgresql://".to_string());
+        }
+        if self.database.max_connections == 0 {
+            erros.push("DATABASE_MAX_CONNECTIONS deve ser maior que zero".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            erros.push(
+                "DATABASE_MIN_CONNECTIONS não pode ser maior que DATABASE_MAX_CONNECTIONS"
+                    .to_string(),
+            );
+        }
+        if self.database.acquire_timeout_secs == 0 {
+            erros.push("DATABASE_ACQUIRE_TIMEOUT_SECS deve ser maior que zero".to_string());
+        }
+
+        if SocketAddr::from_str(&self.server.bind_address).is_err() {
+            erros.push(format!(
+                "BIND_ADDRESS inválido: {:?} (esperado ip:porta)",
+                self.server.bind_address
+            ));
+        }
+        if self.server.workers == Some(0) {
+            erros.push("WORKERS deve ser maior que zero".to_string());
+        }
+        if self.server.cors_origins.is_empty() {
+            erros.push("CORS_ORIGINS não pode ser vazio (use * para liberar tudo)".to_string());
+        }
+        for origem in self.server.cors_origins.iter().filter(|o| *o != "*") {
+            if !origem.starts_with("http://") && !origem.starts_with("https://") {
+                erros.push(format!("origem CORS inválida: {:?}", origem));
+            }
+        }
+
+        if self.auth.jwt_secret.len() < 32 {
+            erros.push("JWT_SECRET é obrigatório e deve ter pelo menos 32 caracteres".to_string());
+        }
+        if self.auth.access_token_ttl_secs == 0 {
+            erros.push("JWT_ACCESS_TTL_SECS deve ser maior que zero".to_string());
+        }
+        if self.auth.refresh_token_ttl_secs <= self.auth.access_token_ttl_secs {
+            erros.push("JWT_REFRESH_TTL_SECS deve ser maior que JWT_ACCESS_TTL_SECS".to_string());
+        }
+
+        if self.midia.tamanho_maximo_bytes == 0 {
+            erros.push("MIDIA_TAMANHO_MAXIMO_BYTES deve ser maior que zero".to_string());
+        }
+        if self.midia.backend == BackendMidia::S3 {
+            let s3 = &self.midia.s3;
+            if !s3.endpoint.starts_with("http://") && !s3.endpoint.starts_with("https://") {
+                erros.push("S3_ENDPOINT é obrigatório e deve começar com http:// ou https://".to_string());
+            }
+            if s3.bucket.is_empty() {
+                erros.push("S3_BUCKET é obrigatório".to_string());
+            }
+            if s3.region.is_empty() {
+                erros.push("S3_REGION não pode ser vazio".to_string());
+            }
+            if s3.access_key.is_empty() || s3.secret_key.is_empty() {
+                erros.push("S3_ACCESS_KEY e S3_SECRET_KEY são obrigatórios".to_string());
+            }
+        }
+
+        // Aceita a sintaxe do env_logger, ex.: "info" ou "info,sqlx=warn"
+        for diretiva in self.log_level.split(',') {
+            let nivel = diretiva.rsplit('=').next().unwrap_or_default().trim();
+            if !NIVEIS_LOG.contains(&nivel.to_ascii_lowercase().as_str()) {
+                erros.push(format!("LOG_LEVEL inválido: {:?}", diretiva));
+            }
+        }
+
+        if erros.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Validacao(erros))
+        }
+    }
+}
+
+fn env_string(var: &'static str) -> Option<String> {
+    std::env::var(var).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn env_parse<T>(var: &'static str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    match env_string(var) {
+        Some(valor) => valor
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e: T::Err| ConfigError::Ambiente {
+                var,
+                mensagem: format!("{:?}: {}", valor, e),
+            }),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,421 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDateTime;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use crate::paginacao::{self, Pagina, PaginaQuery};
+use crate::sessoes::Sessao;
+use crate::validacao;
+
+// Créditos têm duas casas decimais; arredondamento comercial (meio para cima)
+const CASAS_CREDITO: u32 = 2;
+
+// Quanto o saldo pode ficar negativo ao reservar créditos para uma sessão; é o que
+// permite a quem acabou de chegar aprender antes de ensinar
+const LIMITE_NEGATIVO: i64 = 50;
+
+// Espaço das chaves de pg_advisory_xact_lock(bigint) usadas para travar a conta de um usuário
+const TRAVA_CONTA: i64 = 2 << 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum TipoTransacao {
+    // Pagamento direto das aulas na conclusão, usado antes da custódia existir
+    Sessao,
+    // Créditos do aluno reservados na confirmação da sessão
+    Retencao,
+    // Créditos reservados entregues a quem ensinou
+    Liberacao,
+    // Créditos reservados devolvidos ao aluno
+    Estorno,
+}
+
+impl TipoTransacao {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TipoTransacao::Sessao => "sessao",
+            TipoTransacao::Retencao => "retencao",
+            TipoTransacao::Liberacao => "liberacao",
+            TipoTransacao::Estorno => "estorno",
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Saldo {
+    id_users: i32,
+    // Soma dos lançamentos da conta; pode ficar negativo
+    #[schema(value_type = String, example = "12.50")]
+    saldo: Decimal,
+    // Já descontado do saldo, reservado para sessões confirmadas em que o usuário aprende
+    #[schema(value_type = String, example = "7.50")]
+    em_custodia: Decimal,
+}
+
+// Linha do extrato
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Lancamento {
+    id: i64,
+    id_transacao: i64,
+    tipo: TipoTransacao,
+    id_sessao: Option<i32>,
+    descricao: String,
+    // Positivo para créditos recebidos, negativo para débitos
+    #[schema(value_type = String, example = "-7.50")]
+    valor: Decimal,
+    // Saldo da conta logo após o lançamento
+    #[schema(value_type = String, example = "12.50")]
+    saldo: Decimal,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Clone, Copy)]
+pub enum Conta {
+    Usuario(i32),
+    // Conta do sistema que guarda os créditos reservados para sessões confirmadas
+    Custodia,
+}
+
+// Um lado de uma transação ainda não gravada
+pub struct Partida {
+    pub conta: Conta,
+    pub valor: Decimal,
+    pub descricao: String,
+}
+
+// Aula de uma sessão, com o preço calculado pela oferta de quem ensina
+pub struct Aula {
+    pub id_professor: i32,
+    pub id_aluno: i32,
+    pub nome: String,
+    pub preco: Decimal,
+}
+
+// Créditos por uma aula: `valor` da oferta é o preço por hora
+pub fn preco_aula(valor_hora: Decimal, duracao_minutos: i32) -> Decimal {
+    (valor_hora * Decimal::from(duracao_minutos) / Decimal::from(60))
+        .round_dp_with_strategy(CASAS_CREDITO, RoundingStrategy::MidpointAwayFromZero)
+}
+
+// O zero vem do banco sem casas decimais; a API sempre responde com centavos
+fn com_centavos(mut valor: Decimal) -> Decimal {
+    valor.rescale(CASAS_CREDITO);
+    valor
+}
+
+// Conta do usuário, criada no primeiro uso
+async fn id_conta(tx: &mut sqlx::PgConnection, conta: Conta) -> Result<i32, ApiError> {
+    let id_users = match conta {
+        Conta::Usuario(id_users) => id_users,
+        Conta::Custodia => {
+            let id = sqlx::query_scalar::<_, i32>(
+                "SELECT id FROM public.conta_credito WHERE sistema = 'custodia'",
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            return Ok(id);
+        }
+    };
+
+    sqlx::query(
+        "INSERT INTO public.conta_credito (id_users) VALUES ($1) ON CONFLICT (id_users) DO NOTHING",
+    )
+    .bind(id_users)
+    .execute(&mut *tx)
+    .await?;
+
+    let id = sqlx::query_scalar::<_, i32>("SELECT id FROM public.conta_credito WHERE id_users = $1")
+        .bind(id_users)
+        .fetch_one(&mut *tx)
+        .await?;
+    Ok(id)
+}
+
+// Serializa, até o fim da transação, operações que dependem do saldo dos usuários
+pub async fn travar_contas(tx: &mut sqlx::PgConnection, ids_users: &[i32]) -> Result<(), ApiError> {
+    sqlx::query(
+        "SELECT pg_advisory_xact_lock($1 + id) FROM (SELECT DISTINCT unnest($2::int4[]) AS id) AS u ORDER BY id",
+    )
+    .bind(TRAVA_CONTA)
+    .bind(ids_users)
+    .execute(&mut *tx)
+    .await?;
+    Ok(())
+}
+
+pub async fn saldo(executor: impl sqlx::PgExecutor<'_>, id_users: i32) -> Result<Decimal, ApiError> {
+    let query = r#"
+        SELECT COALESCE(SUM(l.valor), 0.00)
+        FROM public.lancamento_credito l
+        JOIN public.conta_credito c ON c.id = l.id_conta
+        WHERE c.id_users = $1
+    "#;
+
+    let saldo = sqlx::query_scalar::<_, Decimal>(query)
+        .bind(id_users)
+        .fetch_one(executor)
+        .await?;
+    Ok(saldo)
+}
+
+// Saldo mínimo depois de reservar créditos
+pub fn saldo_minimo() -> Decimal {
+    -Decimal::from(LIMITE_NEGATIVO)
+}
+
+// Grava uma transação; as partidas precisam somar zero
+pub async fn registrar(
+    tx: &mut sqlx::PgConnection,
+    tipo: TipoTransacao,
+    id_sessao: Option<i32>,
+    partidas: &[Partida],
+) -> Result<i64, ApiError> {
+    let total: Decimal = partidas.iter().map(|p| p.valor).sum();
+    if total != Decimal::ZERO {
+        log::error!("Transação de créditos desbalanceada: {} (total {})", tipo.as_str(), total);
+        return Err(ApiError::Internal);
+    }
+
+    let id_transacao = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO public.transacao_credito (tipo, id_sessao) VALUES ($1, $2) RETURNING id",
+    )
+    .bind(tipo)
+    .bind(id_sessao)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    for partida in partidas.iter().filter(|p| !p.valor.is_zero()) {
+        let id_conta = id_conta(tx, partida.conta).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO public.lancamento_credito (id_transacao, id_conta, valor, descricao)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(id_transacao)
+        .bind(id_conta)
+        .bind(partida.valor)
+        .bind(&partida.descricao)
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(id_transacao)
+}
+
+// Aulas da sessão pelo valor atual das ofertas. Sem a oferta (removida depois da
+// proposta), a aula não é cobrada
+pub async fn aulas_da_sessao(
+    tx: &mut sqlx::PgConnection,
+    sessao: &Sessao,
+) -> Result<Vec<Aula>, ApiError> {
+    let lados = [
+        (sessao.id_proponente, sessao.id_convidado, sessao.id_sub_habilidade_proponente),
+        (sessao.id_convidado, sessao.id_proponente, sessao.id_sub_habilidade_convidado),
+    ];
+
+    let mut aulas = Vec::new();
+    for (id_professor, id_aluno, id_sub_habilidade) in lados {
+        let Some(id_sub_habilidade) = id_sub_habilidade else {
+            continue;
+        };
+
+        let query = r#"
+            SELECT o.valor, s.nome
+            FROM public.usuario_sub_habilidade o
+            JOIN public.sub_habilidade s ON s.id = o.id_sub_habilidade
+            WHERE o.id_users = $1 AND o.id_sub_habilidade = $2
+        "#;
+
+        let Some((valor_hora, nome)) = sqlx::query_as::<_, (Decimal, String)>(query)
+            .bind(id_professor)
+            .bind(id_sub_habilidade)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            continue;
+        };
+
+        let preco = preco_aula(valor_hora, sessao.duracao_minutos);
+        if !preco.is_zero() {
+            aulas.push(Aula {
+                id_professor,
+                id_aluno,
+                nome,
+                preco,
+            });
+        }
+    }
+    Ok(aulas)
+}
+
+// Trava as contas dos alunos e confere se cada um pode pagar as suas aulas sem
+// passar do limite negativo
+pub async fn garantir_saldo(tx: &mut sqlx::PgConnection, aulas: &[Aula]) -> Result<(), ApiError> {
+    let alunos: Vec<i32> = aulas.iter().map(|a| a.id_aluno).collect();
+    travar_contas(tx, &alunos).await?;
+
+    for id_aluno in &alunos {
+        let devido: Decimal = aulas
+            .iter()
+            .filter(|a| a.id_aluno == *id_aluno)
+            .map(|a| a.preco)
+            .sum();
+        if saldo(&mut *tx, *id_aluno).await? - devido < saldo_minimo() {
+            return Err(ApiError::Conflict(format!(
+                "O usuário {} não tem créditos suficientes para esta sessão",
+                id_aluno
+            )));
+        }
+    }
+    Ok(())
+}
+
+// GET: Saldo de créditos do usuário
+#[utoipa::path(
+    get,
+    path = "/creditos/usuario/{id_users}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Saldo calculado a partir dos lançamentos", body = Saldo),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 500, description = "Erro ao calcular saldo", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_saldo(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    let em_custodia = sqlx::query_scalar::<_, Decimal>(
+        r#"
+        SELECT COALESCE(SUM(valor), 0.00) FROM public.custodia_credito
+        WHERE id_aluno = $1 AND status IN ('retida', 'congelada')
+        "#,
+    )
+    .bind(id_users)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(Saldo {
+        id_users,
+        saldo: com_centavos(saldo(pool.get_ref(), id_users).await?),
+        em_custodia: com_centavos(em_custodia),
+    }))
+}
+
+// GET: Extrato de créditos do usuário, dos lançamentos mais recentes para os mais antigos
+#[utoipa::path(
+    get,
+    path = "/creditos/usuario/{id_users}/extrato",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário"),
+        PaginaQuery
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Lançamentos da conta, com o saldo após cada um", body = PaginaLancamentos),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar extrato", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_extrato(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    params: web::Query<PaginaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+    validacao::validar(&*params)?;
+    let after = paginacao::decodificar::<i64>(params.after.as_deref())?;
+
+    let query = r#"
+        SELECT * FROM (
+            SELECT
+                l.id, l.id_transacao, t.tipo, t.id_sessao, l.descricao, l.valor,
+                SUM(l.valor) OVER (ORDER BY l.id) AS saldo,
+                t.created_at
+            FROM public.lancamento_credito l
+            JOIN public.transacao_credito t ON t.id = l.id_transacao
+            JOIN public.conta_credito c ON c.id = l.id_conta
+            WHERE c.id_users = $1
+        ) AS extrato
+        WHERE $2::int8 IS NULL OR id < $2
+        ORDER BY id DESC
+        LIMIT $3
+    "#;
+
+    let lancamentos = sqlx::query_as::<_, Lancamento>(query)
+        .bind(id_users)
+        .bind(after)
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(lancamentos, params.limit, |l| l.id)?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(valor: &str) -> Decimal {
+        valor.parse().unwrap()
+    }
+
+    #[test]
+    fn preco_de_horas_inteiras() {
+        assert_eq!(preco_aula(dec("50.00"), 60), dec("50.00"));
+        assert_eq!(preco_aula(dec("25"), 120), dec("50.00"));
+        assert_eq!(preco_aula(dec("25"), 90), dec("37.50"));
+    }
+
+    #[test]
+    fn preco_de_fracoes_de_hora() {
+        assert_eq!(preco_aula(dec("50"), 45), dec("37.50"));
+        // 33,33 × 0,75 = 24,9975
+        assert_eq!(preco_aula(dec("33.33"), 45), dec("25.00"));
+        // 10 × 50/60 = 8,333...
+        assert_eq!(preco_aula(dec("10"), 50), dec("8.33"));
+        // 19,99 × 50/60 = 16,658...
+        assert_eq!(preco_aula(dec("19.99"), 50), dec("16.66"));
+        // 7 × 45/60 = 5,25
+        assert_eq!(preco_aula(dec("7"), 45), dec("5.25"));
+        assert_eq!(preco_aula(dec("1"), 1), dec("0.02"));
+    }
+
+    #[test]
+    fn arredonda_meio_centavo_para_cima() {
+        // 0,03 × 50/60 = 0,025: o arredondamento bancário daria 0,02
+        assert_eq!(preco_aula(dec("0.03"), 50), dec("0.03"));
+        // 0,01 × 30/60 = 0,005
+        assert_eq!(preco_aula(dec("0.01"), 30), dec("0.01"));
+        // 0,01 × 29/60 = 0,00483...
+        assert_eq!(preco_aula(dec("0.01"), 29), Decimal::ZERO);
+    }
+
+    #[test]
+    fn preco_tem_no_maximo_dois_decimais() {
+        for (valor, minutos) in [("10", 50), ("33.33", 45), ("12.34", 37), ("99.99", 1)] {
+            assert!(preco_aula(dec(valor), minutos).scale() <= CASAS_CREDITO);
+        }
+    }
+}
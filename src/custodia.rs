@@ -0,0 +1,539 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, NaiveDateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use crate::auth::{AuthUser, Moderador};
+use crate::creditos::{self, Conta, Partida, TipoTransacao};
+use crate::error::ApiError;
+use crate::paginacao::{self, Pagina};
+use crate::sessoes::{self, Sessao, StatusSessao};
+use crate::validacao::{self, JsonValidado};
+
+// Cancelamentos com pelo menos essa antecedência devolvem os créditos ao aluno
+pub const ANTECEDENCIA_CANCELAMENTO_HORAS: i64 = 24;
+
+// Depois do término, prazo para confirmar, informar ausência ou abrir disputa;
+// passado esse tempo a custódia é resolvida automaticamente
+pub const PRAZO_CONTESTACAO_HORAS: i64 = 72;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum StatusCustodia {
+    Retida,
+    Congelada,
+    Liberada,
+    Estornada,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Custodia {
+    id: i32,
+    id_sessao: i32,
+    id_aluno: i32,
+    id_professor: i32,
+    // Sub-habilidade ensinada, como estava na confirmação
+    aula: String,
+    #[schema(value_type = String, example = "7.50")]
+    valor: Decimal,
+    status: StatusCustodia,
+    created_at: NaiveDateTime,
+    resolvida_em: Option<NaiveDateTime>,
+}
+
+const SELECT_CUSTODIA: &str = r#"
+    SELECT id, id_sessao, id_aluno, id_professor, aula, valor, status, created_at, resolvida_em
+    FROM public.custodia_credito
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum StatusDisputa {
+    Aberta,
+    Resolvida,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum DecisaoDisputa {
+    // Créditos para quem ensinou
+    Liberar,
+    // Créditos de volta aos alunos
+    Estornar,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Disputa {
+    id: i32,
+    id_sessao: i32,
+    id_autor: i32,
+    motivo: String,
+    status: StatusDisputa,
+    decisao: Option<DecisaoDisputa>,
+    observacao: Option<String>,
+    id_moderador: Option<i32>,
+    created_at: NaiveDateTime,
+    resolvida_em: Option<NaiveDateTime>,
+}
+
+const SELECT_DISPUTA: &str = r#"
+    SELECT
+        id, id_sessao, id_autor, motivo, status, decisao, observacao,
+        id_moderador, created_at, resolvida_em
+    FROM public.disputa_sessao
+"#;
+
+#[derive(Serialize, ToSchema)]
+pub struct CustodiaSessao {
+    itens: Vec<Custodia>,
+    disputa: Option<Disputa>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct DisputaRequest {
+    #[validate(
+        length(min = 1, max = 1000, message = "O motivo deve ter entre 1 e 1000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    motivo: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ResolverDisputaRequest {
+    decisao: DecisaoDisputa,
+    #[validate(
+        length(min = 1, max = 1000, message = "A observação deve ter entre 1 e 1000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    observacao: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct FilaDisputasQuery {
+    // Padrão: aberta
+    status: Option<StatusDisputa>,
+    // Itens por página (1 a 100, padrão 20)
+    #[validate(range(min = 1, max = 100, message = "limit deve estar entre 1 e 100"))]
+    #[serde(default = "paginacao::limit_padrao")]
+    limit: i64,
+    // Valor de `next_cursor` recebido na página anterior
+    after: Option<String>,
+}
+
+// Para quem vão os créditos de uma linha da custódia
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Destino {
+    Professor,
+    Aluno,
+}
+
+// Reserva o preço de cada aula da sessão recém-confirmada. O aluno precisa ter saldo
+// (até o limite negativo); as contas ficam travadas até o fim da transação
+pub async fn reter(tx: &mut sqlx::PgConnection, sessao: &Sessao) -> Result<(), ApiError> {
+    let aulas = creditos::aulas_da_sessao(tx, sessao).await?;
+    if aulas.is_empty() {
+        return Ok(());
+    }
+
+    creditos::garantir_saldo(tx, &aulas).await?;
+
+    let mut partidas = Vec::new();
+    for aula in &aulas {
+        partidas.push(Partida {
+            conta: Conta::Usuario(aula.id_aluno),
+            valor: -aula.preco,
+            descricao: format!("Aula de {} reservada para a sessão {}", aula.nome, sessao.id),
+        });
+        partidas.push(Partida {
+            conta: Conta::Custodia,
+            valor: aula.preco,
+            descricao: format!("Aula de {} reservada para a sessão {}", aula.nome, sessao.id),
+        });
+    }
+    let id_transacao =
+        creditos::registrar(tx, TipoTransacao::Retencao, Some(sessao.id), &partidas).await?;
+
+    for aula in aulas {
+        sqlx::query(
+            r#"
+            INSERT INTO public.custodia_credito
+                (id_sessao, id_aluno, id_professor, aula, valor, id_transacao_retencao)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(sessao.id)
+        .bind(aula.id_aluno)
+        .bind(aula.id_professor)
+        .bind(&aula.nome)
+        .bind(aula.preco)
+        .bind(id_transacao)
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(())
+}
+
+// Tira da custódia as linhas da sessão no status `origem`, mandando cada uma para o
+// destino escolhido. Liberações e estornos viram transações separadas
+async fn resolver(
+    tx: &mut sqlx::PgConnection,
+    id_sessao: i32,
+    origem: StatusCustodia,
+    destino: impl Fn(&Custodia) -> Destino,
+) -> Result<(), ApiError> {
+    let query = format!("{} WHERE id_sessao = $1 AND status = $2 ORDER BY id FOR UPDATE", SELECT_CUSTODIA);
+
+    let itens = sqlx::query_as::<_, Custodia>(&query)
+        .bind(id_sessao)
+        .bind(origem)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    for (para, tipo, status) in [
+        (Destino::Professor, TipoTransacao::Liberacao, StatusCustodia::Liberada),
+        (Destino::Aluno, TipoTransacao::Estorno, StatusCustodia::Estornada),
+    ] {
+        let grupo: Vec<&Custodia> = itens.iter().filter(|c| destino(c) == para).collect();
+        if grupo.is_empty() {
+            continue;
+        }
+
+        let mut partidas = Vec::new();
+        for item in &grupo {
+            let (id_users, descricao) = match para {
+                Destino::Professor => (
+                    item.id_professor,
+                    format!("Aula de {} ensinada na sessão {}", item.aula, id_sessao),
+                ),
+                Destino::Aluno => (
+                    item.id_aluno,
+                    format!("Aula de {} devolvida da sessão {}", item.aula, id_sessao),
+                ),
+            };
+            partidas.push(Partida {
+                conta: Conta::Custodia,
+                valor: -item.valor,
+                descricao: descricao.clone(),
+            });
+            partidas.push(Partida {
+                conta: Conta::Usuario(id_users),
+                valor: item.valor,
+                descricao,
+            });
+        }
+        let id_transacao = creditos::registrar(tx, tipo, Some(id_sessao), &partidas).await?;
+
+        let ids: Vec<i32> = grupo.iter().map(|c| c.id).collect();
+        sqlx::query(
+            r#"
+            UPDATE public.custodia_credito
+            SET status = $2, id_transacao_resolucao = $3, resolvida_em = now()
+            WHERE id = ANY($1)
+            "#,
+        )
+        .bind(&ids)
+        .bind(status)
+        .bind(id_transacao)
+        .execute(&mut *tx)
+        .await?;
+    }
+    Ok(())
+}
+
+// Sessão concluída: os créditos reservados vão para quem ensinou. O pagamento é só o
+// que foi retido na confirmação; sem custódia (aulas gratuitas) nada é transferido
+pub async fn concluir(tx: &mut sqlx::PgConnection, sessao: &Sessao) -> Result<(), ApiError> {
+    resolver(tx, sessao.id, StatusCustodia::Retida, |_| Destino::Professor).await
+}
+
+// Reagendamento de uma sessão confirmada: a nova confirmação reserva de novo
+pub async fn estornar(tx: &mut sqlx::PgConnection, id_sessao: i32) -> Result<(), ApiError> {
+    resolver(tx, id_sessao, StatusCustodia::Retida, |_| Destino::Aluno).await
+}
+
+// Com antecedência, tudo volta aos alunos. Em cima da hora, o aluno que cancelou
+// perde o que reservou para quem ia ensinar; se quem cancelou ensinaria, o aluno é reembolsado
+pub async fn cancelar(
+    tx: &mut sqlx::PgConnection,
+    sessao: &Sessao,
+    id_cancelador: i32,
+) -> Result<(), ApiError> {
+    let antecedencia = sessao.inicio - Utc::now();
+    let no_prazo = antecedencia >= Duration::hours(ANTECEDENCIA_CANCELAMENTO_HORAS);
+
+    resolver(tx, sessao.id, StatusCustodia::Retida, |item| {
+        if no_prazo || item.id_aluno != id_cancelador {
+            Destino::Aluno
+        } else {
+            Destino::Professor
+        }
+    })
+    .await
+}
+
+// Ausência não contestada: quem faltou perde o que reservou como aluno e não recebe
+// pelo que ensinaria
+pub async fn resolver_ausencia(tx: &mut sqlx::PgConnection, sessao: &Sessao) -> Result<(), ApiError> {
+    let id_ausente = sessao.id_ausente;
+    resolver(tx, sessao.id, StatusCustodia::Retida, |item| {
+        if Some(item.id_professor) == id_ausente {
+            Destino::Aluno
+        } else {
+            Destino::Professor
+        }
+    })
+    .await
+}
+
+// GET: Créditos reservados para a sessão e a disputa, se houver
+#[utoipa::path(
+    get,
+    path = "/sessoes/{id}/custodia",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Aulas reservadas, com o destino dos créditos", body = CustodiaSessao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar custódia", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_custodia(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_sessao = path.into_inner();
+
+    let (id_proponente, id_convidado) = sqlx::query_as::<_, (i32, i32)>(
+        "SELECT id_proponente, id_convidado FROM public.sessao_troca WHERE id = $1",
+    )
+    .bind(id_sessao)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Sessão não encontrada".to_string()))?;
+
+    auth.garantir_participante(id_proponente, id_convidado)?;
+
+    let query = format!("{} WHERE id_sessao = $1 ORDER BY id", SELECT_CUSTODIA);
+    let itens = sqlx::query_as::<_, Custodia>(&query)
+        .bind(id_sessao)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let query = format!("{} WHERE id_sessao = $1", SELECT_DISPUTA);
+    let disputa = sqlx::query_as::<_, Disputa>(&query)
+        .bind(id_sessao)
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(CustodiaSessao { itens, disputa }))
+}
+
+// POST: Abre uma disputa; os créditos reservados ficam congelados até a decisão
+#[utoipa::path(
+    post,
+    path = "/sessoes/{id}/disputa",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    request_body = DisputaRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Disputa aberta", body = Disputa),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sessão que ainda não começou, sem créditos retidos ou já disputada", body = ProblemDetails),
+        (status = 422, description = "Motivo ausente", body = ProblemDetails),
+        (status = 500, description = "Erro ao abrir disputa", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn abrir_disputa(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    dados: JsonValidado<DisputaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool.begin().await?;
+    let sessao = sessoes::travar_sessao(&mut tx, path.into_inner(), &auth).await?;
+    sessoes::exigir_status(&sessao, &[StatusSessao::Confirmada, StatusSessao::NaoCompareceu])?;
+    if Utc::now() < sessao.inicio {
+        return Err(ApiError::Conflict("A sessão ainda não começou".to_string()));
+    }
+
+    let disputada = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM public.disputa_sessao WHERE id_sessao = $1)",
+    )
+    .bind(sessao.id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if disputada {
+        return Err(ApiError::Conflict("Esta sessão já tem uma disputa".to_string()));
+    }
+
+    let congeladas = sqlx::query(
+        "UPDATE public.custodia_credito SET status = 'congelada' WHERE id_sessao = $1 AND status = 'retida'",
+    )
+    .bind(sessao.id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if congeladas == 0 {
+        return Err(ApiError::Conflict(
+            "Não há créditos retidos nesta sessão".to_string(),
+        ));
+    }
+
+    let query = format!(
+        r#"
+        WITH nova AS (
+            INSERT INTO public.disputa_sessao (id_sessao, id_autor, motivo)
+            VALUES ($1, $2, $3)
+            RETURNING *
+        )
+        {}
+        "#,
+        SELECT_DISPUTA.replace("public.disputa_sessao", "nova")
+    );
+
+    let disputa = sqlx::query_as::<_, Disputa>(&query)
+        .bind(sessao.id)
+        .bind(auth.id)
+        .bind(dados.motivo.trim())
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    log::info!("Disputa {} aberta na sessão {} pelo usuário {}", disputa.id, sessao.id, auth.id);
+    Ok(HttpResponse::Created().json(disputa))
+}
+
+// GET: Fila de disputas para moderação, das mais antigas para as mais novas
+#[utoipa::path(
+    get,
+    path = "/moderacao/disputas",
+    params(FilaDisputasQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Disputas no status pedido. Requer papel moderator ou admin.", body = PaginaDisputas),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar disputas", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_fila_disputas(
+    pool: web::Data<PgPool>,
+    _moderador: Moderador,
+    params: web::Query<FilaDisputasQuery>,
+) -> Result<HttpResponse, ApiError> {
+    validacao::validar(&*params)?;
+    let after = paginacao::decodificar::<i32>(params.after.as_deref())?;
+
+    let query = format!(
+        "{} WHERE status = $1 AND ($2::int4 IS NULL OR id > $2) ORDER BY id LIMIT $3",
+        SELECT_DISPUTA
+    );
+
+    let disputas = sqlx::query_as::<_, Disputa>(&query)
+        .bind(params.status.unwrap_or(StatusDisputa::Aberta))
+        .bind(after)
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(disputas, params.limit, |d| d.id)?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
+
+// POST: Decide a disputa, liberando os créditos congelados para quem ensinou ou devolvendo aos alunos
+#[utoipa::path(
+    post,
+    path = "/moderacao/disputas/{id}/resolver",
+    params(
+        ("id" = i32, Path, description = "ID da disputa")
+    ),
+    request_body = ResolverDisputaRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Disputa resolvida. Requer papel moderator ou admin.", body = Disputa),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 404, description = "Disputa não encontrada", body = ProblemDetails),
+        (status = 409, description = "Disputa já resolvida", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao resolver disputa", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn resolver_disputa(
+    pool: web::Data<PgPool>,
+    Moderador(moderador): Moderador,
+    path: web::Path<i32>,
+    dados: JsonValidado<ResolverDisputaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let mut tx = pool.begin().await?;
+
+    let query = format!("{} WHERE id = $1 FOR UPDATE", SELECT_DISPUTA);
+    let disputa = sqlx::query_as::<_, Disputa>(&query)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Disputa não encontrada".to_string()))?;
+
+    if disputa.status != StatusDisputa::Aberta {
+        return Err(ApiError::Conflict("Disputa já foi resolvida".to_string()));
+    }
+
+    let destino = match dados.decisao {
+        DecisaoDisputa::Liberar => Destino::Professor,
+        DecisaoDisputa::Estornar => Destino::Aluno,
+    };
+    resolver(&mut tx, disputa.id_sessao, StatusCustodia::Congelada, |_| destino).await?;
+
+    let query = format!(
+        r#"
+        WITH resolvida AS (
+            UPDATE public.disputa_sessao
+            SET status = 'resolvida', decisao = $2, observacao = $3,
+                id_moderador = $4, resolvida_em = now()
+            WHERE id = $1
+            RETURNING *
+        )
+        {}
+        "#,
+        SELECT_DISPUTA.replace("public.disputa_sessao", "resolvida")
+    );
+
+    let disputa = sqlx::query_as::<_, Disputa>(&query)
+        .bind(id)
+        .bind(dados.decisao)
+        .bind(dados.observacao.as_deref().map(str::trim))
+        .bind(moderador.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    log::info!("Disputa {} resolvida pelo usuário {}", id, moderador.id);
+    Ok(HttpResponse::Ok().json(disputa))
+}
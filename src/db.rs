@@ -0,0 +1,22 @@
+use crate::config::DatabaseConfig;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
+// Migrations embutidas no binário a partir da pasta ./migrations
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+pub async fn conectar(config: &DatabaseConfig) -> Result<PgPool, sqlx::Error> {
+    PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(config.acquire_timeout())
+        .idle_timeout(config.idle_timeout())
+        .connect(&config.url)
+        .await
+}
+
+pub async fn migrar(pool: &PgPool) -> Result<(), sqlx::migrate::MigrateError> {
+    MIGRATOR.run(pool).await?;
+    log::info!("Migrations aplicadas com sucesso");
+    Ok(())
+}
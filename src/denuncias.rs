@@ -0,0 +1,423 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::auth::{AuthUser, Moderador};
+use crate::bloqueios;
+use crate::error::{ApiError, FieldViolation};
+use crate::paginacao::{self, Pagina};
+use crate::validacao::{self, JsonValidado, Referencias};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum CategoriaDenuncia {
+    Assedio,
+    Spam,
+    PerfilFalso,
+    ConteudoImproprio,
+    Golpe,
+    // Faltas repetidas a sessões de troca
+    Ausencia,
+    Outro,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum StatusDenuncia {
+    Pendente,
+    Procedente,
+    Improcedente,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Denuncia {
+    id: i32,
+    // null se a conta do autor foi removida
+    id_autor: Option<i32>,
+    id_denunciado: i32,
+    categoria: CategoriaDenuncia,
+    descricao: String,
+    status: StatusDenuncia,
+    observacao: Option<String>,
+    id_moderador: Option<i32>,
+    created_at: NaiveDateTime,
+    decidida_em: Option<NaiveDateTime>,
+}
+
+const SELECT_DENUNCIA: &str = r#"
+    SELECT
+        id, id_autor, id_denunciado, categoria, descricao, status, observacao,
+        id_moderador, created_at, decidida_em
+    FROM public.denuncia
+"#;
+
+// Mensagem do chat copiada no momento da denúncia; continua visível à moderação
+// mesmo que a conversa seja apagada
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Evidencia {
+    id_mensagem: i64,
+    id_remetente: Option<i32>,
+    conteudo: String,
+    enviada_em: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DenunciaDetalhe {
+    denuncia: Denuncia,
+    evidencias: Vec<Evidencia>,
+    // Denúncias contra o mesmo usuário já julgadas procedentes
+    procedentes_anteriores: i64,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validar_denuncia"))]
+pub struct DenunciaRequest {
+    // Usuário autenticado
+    #[validate(range(min = 1, message = "ID de usuário inválido"))]
+    id_autor: i32,
+    #[validate(range(min = 1, message = "ID de usuário inválido"))]
+    id_denunciado: i32,
+    categoria: CategoriaDenuncia,
+    #[validate(
+        length(min = 1, max = 2000, message = "A descrição deve ter entre 1 e 2000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    descricao: String,
+    // Mensagens da conversa com o denunciado (opcional, até 20)
+    #[serde(default)]
+    #[validate(length(max = 20, message = "No máximo 20 mensagens como evidência"))]
+    id_mensagens: Vec<i64>,
+    // Também bloqueia o denunciado
+    #[serde(default)]
+    bloquear: bool,
+}
+
+fn validar_denuncia(dados: &DenunciaRequest) -> Result<(), ValidationError> {
+    if dados.id_autor == dados.id_denunciado {
+        return Err(validacao::erro_no_campo(
+            "id_denunciado",
+            "self_reference",
+            "O usuário não pode denunciar a si mesmo",
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum DecisaoDenuncia {
+    Procedente,
+    Improcedente,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct DecidirDenunciaRequest {
+    decisao: DecisaoDenuncia,
+    #[validate(
+        length(min = 1, max = 1000, message = "A observação deve ter entre 1 e 1000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    observacao: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct FilaDenunciasQuery {
+    // Padrão: pendente
+    status: Option<StatusDenuncia>,
+    categoria: Option<CategoriaDenuncia>,
+    // Itens por página (1 a 100, padrão 20)
+    #[validate(range(min = 1, max = 100, message = "limit deve estar entre 1 e 100"))]
+    #[serde(default = "paginacao::limit_padrao")]
+    limit: i64,
+    // Valor de `next_cursor` recebido na página anterior
+    after: Option<String>,
+}
+
+// POST: Denuncia um usuário à moderação, com mensagens do chat como evidência
+#[utoipa::path(
+    post,
+    path = "/denuncias",
+    request_body = DenunciaRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Denúncia registrada", body = Denuncia),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 409, description = "Já existe uma denúncia pendente do autor sobre este usuário", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos, usuário inexistente ou mensagem fora da conversa com o denunciado", body = ProblemDetails),
+        (status = 500, description = "Erro ao registrar denúncia", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn criar_denuncia(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    dados: JsonValidado<DenunciaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let dados = dados.into_inner();
+    auth.garantir_dono(dados.id_autor)?;
+
+    Referencias::new(pool.get_ref())
+        .usuario("id_denunciado", dados.id_denunciado)
+        .await?
+        .verificar()?;
+
+    let mut tx = pool.begin().await?;
+
+    let query = format!(
+        r#"
+        WITH nova AS (
+            INSERT INTO public.denuncia (id_autor, id_denunciado, categoria, descricao)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id_autor, id_denunciado) WHERE status = 'pendente' DO NOTHING
+            RETURNING *
+        )
+        {}
+        "#,
+        SELECT_DENUNCIA.replace("public.denuncia", "nova")
+    );
+
+    let denuncia = sqlx::query_as::<_, Denuncia>(&query)
+        .bind(dados.id_autor)
+        .bind(dados.id_denunciado)
+        .bind(dados.categoria)
+        .bind(dados.descricao.trim())
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            ApiError::Conflict(
+                "Já existe uma denúncia sua sobre este usuário aguardando a moderação".to_string(),
+            )
+        })?;
+
+    let mut id_mensagens = dados.id_mensagens;
+    id_mensagens.sort_unstable();
+    id_mensagens.dedup();
+
+    if !id_mensagens.is_empty() {
+        // Só mensagens da conversa entre o autor e o denunciado
+        let copiadas = sqlx::query(
+            r#"
+            INSERT INTO public.denuncia_evidencia
+                (id_denuncia, id_mensagem, id_remetente, conteudo, enviada_em)
+            SELECT $1, m.id, m.id_remetente, m.conteudo, m.created_at
+            FROM public.mensagem m
+            JOIN public.conversa c ON c.id = m.id_conversa
+            WHERE m.id = ANY($2)
+              AND c.id_usuario1 = LEAST($3, $4) AND c.id_usuario2 = GREATEST($3, $4)
+            "#,
+        )
+        .bind(denuncia.id)
+        .bind(&id_mensagens)
+        .bind(dados.id_autor)
+        .bind(dados.id_denunciado)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if copiadas != id_mensagens.len() as u64 {
+            return Err(ApiError::Validation(vec![FieldViolation {
+                field: "id_mensagens".to_string(),
+                code: "not_found".to_string(),
+                message: "Mensagem não encontrada na conversa com o usuário denunciado".to_string(),
+            }]));
+        }
+    }
+
+    if dados.bloquear {
+        bloqueios::bloquear(&mut tx, dados.id_autor, dados.id_denunciado).await?;
+    }
+
+    tx.commit().await?;
+
+    log::info!(
+        "Denúncia {} registrada pelo usuário {} contra o usuário {}",
+        denuncia.id,
+        dados.id_autor,
+        dados.id_denunciado
+    );
+    Ok(HttpResponse::Created().json(denuncia))
+}
+
+// GET: Fila de denúncias para moderação, das mais antigas para as mais novas
+#[utoipa::path(
+    get,
+    path = "/moderacao/denuncias",
+    params(FilaDenunciasQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Denúncias no status pedido. Requer papel moderator ou admin.", body = PaginaDenuncias),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar denúncias", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_fila_denuncias(
+    pool: web::Data<PgPool>,
+    _moderador: Moderador,
+    params: web::Query<FilaDenunciasQuery>,
+) -> Result<HttpResponse, ApiError> {
+    validacao::validar(&*params)?;
+    let after = paginacao::decodificar::<i32>(params.after.as_deref())?;
+
+    let query = format!(
+        r#"
+        {}
+        WHERE status = $1
+          AND ($2::varchar IS NULL OR categoria = $2)
+          AND ($3::int4 IS NULL OR id > $3)
+        ORDER BY id
+        LIMIT $4
+        "#,
+        SELECT_DENUNCIA
+    );
+
+    let denuncias = sqlx::query_as::<_, Denuncia>(&query)
+        .bind(params.status.unwrap_or(StatusDenuncia::Pendente))
+        .bind(params.categoria)
+        .bind(after)
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(denuncias, params.limit, |d| d.id)?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
+
+// GET: Denúncia com as evidências e o histórico do denunciado
+#[utoipa::path(
+    get,
+    path = "/moderacao/denuncias/{id}",
+    params(
+        ("id" = i32, Path, description = "ID da denúncia")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Denúncia e evidências. Requer papel moderator ou admin.", body = DenunciaDetalhe),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 404, description = "Denúncia não encontrada", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar denúncia", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_denuncia(
+    pool: web::Data<PgPool>,
+    _moderador: Moderador,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+
+    let query = format!("{} WHERE id = $1", SELECT_DENUNCIA);
+    let denuncia = sqlx::query_as::<_, Denuncia>(&query)
+        .bind(id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Denúncia não encontrada".to_string()))?;
+
+    let evidencias = sqlx::query_as::<_, Evidencia>(
+        r#"
+        SELECT id_mensagem, id_remetente, conteudo, enviada_em
+        FROM public.denuncia_evidencia
+        WHERE id_denuncia = $1
+        ORDER BY id_mensagem
+        "#,
+    )
+    .bind(id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let procedentes_anteriores = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM public.denuncia WHERE id_denunciado = $1 AND status = 'procedente' AND id <> $2",
+    )
+    .bind(denuncia.id_denunciado)
+    .bind(id)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(DenunciaDetalhe {
+        denuncia,
+        evidencias,
+        procedentes_anteriores,
+    }))
+}
+
+// POST: Julga a denúncia procedente ou improcedente
+#[utoipa::path(
+    post,
+    path = "/moderacao/denuncias/{id}/decidir",
+    params(
+        ("id" = i32, Path, description = "ID da denúncia")
+    ),
+    request_body = DecidirDenunciaRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Denúncia decidida. Requer papel moderator ou admin.", body = Denuncia),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 404, description = "Denúncia não encontrada", body = ProblemDetails),
+        (status = 409, description = "Denúncia já decidida", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao decidir denúncia", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn decidir_denuncia(
+    pool: web::Data<PgPool>,
+    Moderador(moderador): Moderador,
+    path: web::Path<i32>,
+    dados: JsonValidado<DecidirDenunciaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+
+    // A condição no status impede que duas decisões simultâneas se sobreponham
+    let query = format!(
+        r#"
+        WITH decidida AS (
+            UPDATE public.denuncia
+            SET status = $2, observacao = $3, id_moderador = $4, decidida_em = now()
+            WHERE id = $1 AND status = 'pendente'
+            RETURNING *
+        )
+        {}
+        "#,
+        SELECT_DENUNCIA.replace("public.denuncia", "decidida")
+    );
+
+    let denuncia = sqlx::query_as::<_, Denuncia>(&query)
+        .bind(id)
+        .bind(dados.decisao)
+        .bind(dados.observacao.as_deref().map(str::trim))
+        .bind(moderador.id)
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    let Some(denuncia) = denuncia else {
+        let existe = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM public.denuncia WHERE id = $1)",
+        )
+        .bind(id)
+        .fetch_one(pool.get_ref())
+        .await?;
+        return Err(if existe {
+            ApiError::Conflict("Denúncia já foi decidida".to_string())
+        } else {
+            ApiError::NotFound("Denúncia não encontrada".to_string())
+        });
+    };
+
+    log::info!("Denúncia {} decidida pelo usuário {}", id, moderador.id);
+    Ok(HttpResponse::Ok().json(denuncia))
+}
@@ -0,0 +1,237 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::auth::{self, AuthUser};
+use crate::error::ApiError;
+use crate::validacao::{JsonValidado, Referencias};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum NivelDesejado {
+    Iniciante,
+    Intermediario,
+    Avancado,
+}
+
+// Sub-habilidade que o usuário quer aprender
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Desejada {
+    id_users: i32,
+    first_name: String,
+    // null quando o dono escondeu o sobrenome
+    last_name: Option<String>,
+    id_sub_habilidade: i32,
+    nome_sub_habilidade: String,
+    descricao: String,
+    nivel_desejado: NivelDesejado,
+    // 1 (baixa) a 5 (alta)
+    prioridade: i16,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct DesejadaRequest {
+    #[validate(range(min = 1, message = "ID de usuário inválido"))]
+    id_users: i32,
+    #[validate(range(min = 1, message = "ID de sub-habilidade inválido"))]
+    id_sub_habilidade: i32,
+    #[validate(length(max = 1000, message = "A descrição deve ter no máximo 1000 caracteres"))]
+    #[serde(default)]
+    descricao: String,
+    nivel_desejado: NivelDesejado,
+    #[validate(range(min = 1, max = 5, message = "A prioridade deve estar entre 1 e 5"))]
+    #[serde(default = "prioridade_padrao")]
+    prioridade: i16,
+}
+
+fn prioridade_padrao() -> i16 {
+    3
+}
+
+// POST: Insere uma sub-habilidade desejada
+#[utoipa::path(
+    post,
+    path = "/desejadas/inserir",
+    request_body = DesejadaRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sub-habilidade desejada inserida com sucesso"),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 409, description = "Sub-habilidade já desejada pelo usuário", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos ou usuário/sub-habilidade inexistente", body = ProblemDetails),
+        (status = 500, description = "Erro ao inserir sub-habilidade desejada", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn inserir_desejada(
+    pool: web::Data<sqlx::PgPool>,
+    auth: AuthUser,
+    dados: JsonValidado<DesejadaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.garantir_dono(dados.id_users)?;
+
+    Referencias::new(pool.get_ref())
+        .usuario("id_users", dados.id_users)
+        .await?
+        .sub_habilidade("id_sub_habilidade", dados.id_sub_habilidade)
+        .await?
+        .verificar()?;
+
+    let created_at = Utc::now().naive_utc();
+
+    let query = r#"
+        INSERT INTO public.usuario_sub_habilidade_desejada
+            (id_users, id_sub_habilidade, descricao, nivel_desejado, prioridade, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+    "#;
+
+    sqlx::query(query)
+        .bind(dados.id_users)
+        .bind(dados.id_sub_habilidade)
+        .bind(dados.descricao.trim())
+        .bind(dados.nivel_desejado)
+        .bind(dados.prioridade)
+        .bind(created_at)
+        .execute(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json("Sub-habilidade desejada inserida com sucesso"))
+}
+
+// GET: Retorna as sub-habilidades desejadas de um usuário; vazias para os outros
+// usuários quando ele não as deixou públicas
+#[utoipa::path(
+    get,
+    path = "/desejadas/obter/{id_users}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário")
+    ),
+    security((), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sub-habilidades desejadas, da maior para a menor prioridade", body = [Desejada]),
+        (status = 500, description = "Erro ao buscar sub-habilidades desejadas", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_desejadas(
+    pool: web::Data<sqlx::PgPool>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    // O token é opcional: o dono vê a lista e o sobrenome mesmo quando estão privados
+    let dono = auth::autenticar(&req).is_ok_and(|usuario| usuario.id == id_users);
+
+    let query = r#"
+        SELECT
+            d.id_users,
+            us.first_name,
+            CASE WHEN $2 OR us.sobrenome_publico THEN us.last_name END AS last_name,
+            d.id_sub_habilidade,
+            s.nome AS nome_sub_habilidade,
+            d.descricao,
+            d.nivel_desejado,
+            d.prioridade
+        FROM
+            public.usuario_sub_habilidade_desejada AS d
+        INNER JOIN
+            public.sub_habilidade AS s
+        ON
+            d.id_sub_habilidade = s.id
+        INNER JOIN
+            public.users AS us
+        ON
+            d.id_users = us.id
+        WHERE d.id_users = $1 AND ($2 OR us.desejadas_publicas)
+        ORDER BY d.prioridade DESC, s.nome;
+    "#;
+
+    let dados = sqlx::query_as::<_, Desejada>(query)
+        .bind(id_users)
+        .bind(dono)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(dados))
+}
+
+// PUT: Atualiza descrição, nível e prioridade de uma sub-habilidade desejada
+#[utoipa::path(
+    put,
+    path = "/desejadas/atualizar",
+    request_body = DesejadaRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sub-habilidade desejada atualizada com sucesso"),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 404, description = "Sub-habilidade desejada não encontrada", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao atualizar sub-habilidade desejada", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn atualizar_desejada(
+    pool: web::Data<sqlx::PgPool>,
+    auth: AuthUser,
+    dados: JsonValidado<DesejadaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.garantir_dono(dados.id_users)?;
+
+    let query = r#"
+        UPDATE public.usuario_sub_habilidade_desejada
+        SET descricao = $1, nivel_desejado = $2, prioridade = $3
+        WHERE id_users = $4 AND id_sub_habilidade = $5
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(dados.descricao.trim())
+        .bind(dados.nivel_desejado)
+        .bind(dados.prioridade)
+        .bind(dados.id_users)
+        .bind(dados.id_sub_habilidade)
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound("Sub-habilidade desejada não encontrada".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json("Sub-habilidade desejada atualizada com sucesso"))
+}
+
+// DELETE: Remove todas as sub-habilidades desejadas de um usuário
+#[utoipa::path(
+    delete,
+    path = "/desejadas/deletar/{id_users}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sub-habilidades desejadas deletadas com sucesso"),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 500, description = "Erro ao deletar sub-habilidades desejadas", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn deletar_desejadas(
+    pool: web::Data<sqlx::PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    sqlx::query("DELETE FROM public.usuario_sub_habilidade_desejada WHERE id_users = $1")
+        .bind(id_users)
+        .execute(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json("Sub-habilidades desejadas deletadas com sucesso"))
+}
@@ -0,0 +1,230 @@
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::armazenamento::ErroStorage;
+
+// Códigos SQLSTATE do Postgres tratados de forma específica
+const UNIQUE_VIOLATION: &str = "23505";
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+const NOT_NULL_VIOLATION: &str = "23502";
+const CHECK_VIOLATION: &str = "23514";
+
+// Corpo de erro no formato RFC 7807 (application/problem+json)
+#[derive(Serialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub tipo: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    // Identificador estável para o cliente tratar o erro sem depender do texto
+    pub code: &'static str,
+    // Presente apenas em erros de validação, um item por regra violada
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldViolation>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldViolation {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    InvalidReference(String),
+    #[error("{0}")]
+    ConstraintViolation(String),
+    #[error("{0}")]
+    PayloadTooLarge(String),
+    #[error("{0}")]
+    UnsupportedMediaType(String),
+    #[error("A requisição contém campos inválidos")]
+    Validation(Vec<FieldViolation>),
+    #[error("Serviço temporariamente indisponível")]
+    ServiceUnavailable,
+    #[error("Erro interno do servidor")]
+    Internal,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::InvalidReference(_) => "invalid_reference",
+            ApiError::ConstraintViolation(_) => "constraint_violation",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::UnsupportedMediaType(_) => "unsupported_media_type",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::ServiceUnavailable => "service_unavailable",
+            ApiError::Internal => "internal_error",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "Requisição inválida",
+            ApiError::Unauthorized(_) => "Não autenticado",
+            ApiError::Forbidden(_) => "Acesso negado",
+            ApiError::NotFound(_) => "Recurso não encontrado",
+            ApiError::Conflict(_) => "Conflito com um registro existente",
+            ApiError::InvalidReference(_) => "Referência inválida",
+            ApiError::ConstraintViolation(_) => "Dados inválidos",
+            ApiError::PayloadTooLarge(_) => "Conteúdo muito grande",
+            ApiError::UnsupportedMediaType(_) => "Tipo de arquivo não suportado",
+            ApiError::Validation(_) => "Falha de validação",
+            ApiError::ServiceUnavailable => "Serviço indisponível",
+            ApiError::Internal => "Erro interno",
+        }
+    }
+
+    pub fn problem(&self) -> ProblemDetails {
+        ProblemDetails {
+            tipo: format!("urn:swapp:problema:{}", self.code()),
+            title: self.title().to_string(),
+            status: self.status_code().as_u16(),
+            detail: self.to_string(),
+            code: self.code(),
+            errors: match self {
+                ApiError::Validation(violacoes) => violacoes.clone(),
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::InvalidReference(_)
+            | ApiError::ConstraintViolation(_)
+            | ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut resposta = HttpResponse::build(self.status_code());
+        resposta.insert_header((header::CONTENT_TYPE, "application/problem+json"));
+        if let ApiError::Unauthorized(_) = self {
+            resposta.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        resposta.json(self.problem())
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::RowNotFound => ApiError::NotFound("Registro não encontrado".to_string()),
+            sqlx::Error::Database(db) => {
+                // A mensagem do Postgres cita tabelas e colunas; fica só no log
+                let constraint = db.constraint();
+                let detalhe = |generico: &str| {
+                    log::warn!("Restrição do banco violada: {} ({:?})", db.message(), constraint);
+                    constraint
+                        .and_then(mensagem_constraint)
+                        .unwrap_or(generico)
+                        .to_string()
+                };
+                match db.code().as_deref() {
+                    Some(UNIQUE_VIOLATION) => ApiError::Conflict(detalhe("Registro já existe")),
+                    Some(FOREIGN_KEY_VIOLATION) => ApiError::InvalidReference(detalhe(
+                        "Referência a um registro inexistente",
+                    )),
+                    Some(NOT_NULL_VIOLATION) | Some(CHECK_VIOLATION) => {
+                        ApiError::ConstraintViolation(detalhe("Dados violam uma regra do cadastro"))
+                    }
+                    _ => {
+                        log::error!("Erro do banco de dados: {:?}", e);
+                        ApiError::Internal
+                    }
+                }
+            }
+            sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) => {
+                log::error!("Banco de dados indisponível: {:?}", e);
+                ApiError::ServiceUnavailable
+            }
+            _ => {
+                log::error!("Erro inesperado do banco de dados: {:?}", e);
+                ApiError::Internal
+            }
+        }
+    }
+}
+
+// Mensagem para o cliente de cada restrição conhecida do esquema; as demais
+// recebem um texto genérico conforme o tipo de violação
+fn mensagem_constraint(nome: &str) -> Option<&'static str> {
+    let mensagem = match nome {
+        "users_email_key" => "E-mail já cadastrado",
+        "users_role_check" => "Papel de usuário inválido",
+        "habilidade_nome_key" => "Já existe uma habilidade com este nome",
+        "sub_habilidade_habilidade_nome_key" => {
+            "Já existe uma sub-habilidade com este nome nesta habilidade"
+        }
+        "usuario_sub_habilidade_pkey" => "O usuário já oferece esta sub-habilidade",
+        "usuario_sub_habilidade_desejada_pkey" => "O usuário já deseja esta sub-habilidade",
+        "usuario_sub_habilidade_desejada_nivel_check" => "Nível desejado inválido",
+        "usuario_sub_habilidade_desejada_prioridade_check" => {
+            "A prioridade deve estar entre 1 e 5"
+        }
+        "teste_match_id_deu_like_id_liked_key" => "Like já registrado",
+        "historico_match_id1_id2_key" => "Histórico já registrado para este par",
+        "sugestao_sub_habilidade_pendente_key" => {
+            "Já existe uma sugestão pendente com este nome nesta habilidade"
+        }
+        "conversa_id_usuario1_id_usuario2_key" => "A conversa entre os usuários já existe",
+        "sessao_troca_participantes_check" => "O usuário não pode propor uma sessão a si mesmo",
+        "sessao_troca_duracao_check" => "Duração da sessão fora do intervalo permitido",
+        "sessao_troca_modalidade_check" => "Modalidade da sessão inválida",
+        "avaliacao_sessao_avaliador_key" => "O usuário já avaliou esta sessão",
+        "avaliacao_participantes_check" => "O usuário não pode avaliar a si mesmo",
+        "disputa_sessao_id_sessao_key" => "A sessão já tem uma disputa",
+        "bloqueio_pkey" => "Usuário já bloqueado",
+        "bloqueio_par_check" => "O usuário não pode bloquear a si mesmo",
+        "denuncia_pendente_key" => "Já existe uma denúncia pendente sobre este usuário",
+        "denuncia_par_check" => "O usuário não pode denunciar a si mesmo",
+        "denuncia_categoria_check" => "Categoria de denúncia inválida",
+        _ => return None,
+    };
+    Some(mensagem)
+}
+
+impl From<ErroStorage> for ApiError {
+    fn from(e: ErroStorage) -> Self {
+        log::error!("Erro no armazenamento de arquivos: {}", e);
+        match e {
+            ErroStorage::Io(_) | ErroStorage::Http(_) | ErroStorage::Resposta(..) => {
+                ApiError::ServiceUnavailable
+            }
+            ErroStorage::Configuracao(_) | ErroStorage::ChaveInvalida(_) => ApiError::Internal,
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::PayloadError;
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpResponse};
+use futures_util::stream::{self, Stream};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::pin::Pin;
+
+use crate::auth;
+use crate::error::ApiError;
+
+pub const HEADER: &str = "Idempotency-Key";
+// Indica ao cliente que a resposta veio do armazenamento, não de uma nova execução
+pub const HEADER_REPETIDA: &str = "Idempotent-Replayed";
+
+const TAMANHO_MAXIMO_CHAVE: usize = 255;
+
+#[derive(sqlx::FromRow)]
+struct Registro {
+    fingerprint: String,
+    status: Option<i16>,
+    corpo: Option<Vec<u8>>,
+    content_type: Option<String>,
+}
+
+// Middleware: POST/PUT com Idempotency-Key executam uma única vez por usuário e chave
+// (válida por 24h). Repetições devolvem a resposta guardada.
+pub async fn idempotencia(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let chave = match req.headers().get(HEADER) {
+        Some(valor) if matches!(*req.method(), Method::POST | Method::PUT) => valor
+            .to_str()
+            .ok()
+            .map(str::trim)
+            .filter(|c| !c.is_empty() && c.len() <= TAMANHO_MAXIMO_CHAVE)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "{} deve ter entre 1 e {} caracteres ASCII",
+                    HEADER, TAMANHO_MAXIMO_CHAVE
+                ))
+            })?,
+        _ => return next.call(req).await.map(ServiceResponse::map_into_boxed_body),
+    };
+
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .cloned()
+        .ok_or(ApiError::Internal)?;
+
+    // Lê o corpo para calcular o fingerprint e o devolve à requisição para o handler
+    let corpo = req.extract::<web::Bytes>().await.map_err(|e| {
+        if e.as_response_error().status_code() == StatusCode::PAYLOAD_TOO_LARGE {
+            ApiError::PayloadTooLarge("O corpo da requisição excede o tamanho máximo".to_string())
+                .into()
+        } else {
+            e
+        }
+    })?;
+    let fingerprint = calcular_fingerprint(&req, &corpo);
+    req.set_payload(payload_de(corpo));
+
+    // Chaves são separadas por usuário. Sem login não há como distinguir um cliente de
+    // outro, então requisições anônimas (como /auth/registrar) usam a chave junto com
+    // o fingerprint: só a mesma requisição repetida recebe a resposta guardada. O
+    // escopo leva metade do hash (cabe na coluna); o fingerprint completo é conferido
+    let escopo = match auth::autenticar(req.request()) {
+        Ok(usuario) => format!("usuario:{}", usuario.id),
+        Err(_) => format!("anonimo:{}", &fingerprint[..32]),
+    };
+
+    if !reservar(&pool, &escopo, &chave, &fingerprint).await? {
+        let resposta = repetir(&pool, &escopo, &chave, &fingerprint).await?;
+        return Ok(req.into_response(resposta));
+    }
+
+    let res = match next.call(req).await {
+        Ok(res) => res,
+        Err(e) => {
+            liberar(&pool, &escopo, &chave).await?;
+            return Err(e);
+        }
+    };
+
+    // Erros 5xx não são guardados: o cliente pode tentar de novo com a mesma chave.
+    // Respostas com Cache-Control: no-store (tokens, endereços secretos) também não
+    if res.status().is_server_error() || sem_armazenamento(res.headers()) {
+        liberar(&pool, &escopo, &chave).await?;
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, corpo) = res.into_parts();
+    let corpo = body::to_bytes(corpo).await.map_err(|_| ApiError::Internal)?;
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let query = r#"
+        UPDATE public.idempotency_key
+        SET status = $3, corpo = $4, content_type = $5
+        WHERE escopo = $1 AND chave = $2
+    "#;
+
+    sqlx::query(query)
+        .bind(&escopo)
+        .bind(&chave)
+        .bind(res.status().as_u16() as i16)
+        .bind(corpo.as_ref())
+        .bind(content_type)
+        .execute(pool.get_ref())
+        .await
+        .map_err(ApiError::from)?;
+
+    let res = res.set_body(BoxBody::new(corpo));
+    Ok(ServiceResponse::new(req, res))
+}
+
+fn sem_armazenamento(headers: &header::HeaderMap) -> bool {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|diretiva| diretiva.trim().eq_ignore_ascii_case("no-store"))
+}
+
+fn calcular_fingerprint(req: &ServiceRequest, corpo: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(b" ");
+    hasher.update(req.uri().to_string());
+    hasher.update(b"\n");
+    hasher.update(corpo);
+    hex::encode(hasher.finalize())
+}
+
+type StreamCorpo = Pin<Box<dyn Stream<Item = Result<web::Bytes, PayloadError>>>>;
+
+fn payload_de(corpo: web::Bytes) -> Payload {
+    let stream: StreamCorpo = Box::pin(stream::once(async move { Ok(corpo) }));
+    Payload::from(stream)
+}
+
+// Cria a reserva da chave; também reaproveita chaves expiradas
+async fn reservar(
+    pool: &PgPool,
+    escopo: &str,
+    chave: &str,
+    fingerprint: &str,
+) -> Result<bool, ApiError> {
+    let query = r#"
+        INSERT INTO public.idempotency_key (escopo, chave, fingerprint)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (escopo, chave) DO UPDATE
+        SET fingerprint = EXCLUDED.fingerprint,
+            status = NULL,
+            corpo = NULL,
+            content_type = NULL,
+            created_at = now()
+        WHERE idempotency_key.created_at < now() - INTERVAL '24 hours'
+        RETURNING TRUE
+    "#;
+
+    let reservada = sqlx::query_scalar::<_, bool>(query)
+        .bind(escopo)
+        .bind(chave)
+        .bind(fingerprint)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(reservada.is_some())
+}
+
+async fn repetir(
+    pool: &PgPool,
+    escopo: &str,
+    chave: &str,
+    fingerprint: &str,
+) -> Result<HttpResponse, ApiError> {
+    let query = r#"
+        SELECT fingerprint, status, corpo, content_type
+        FROM public.idempotency_key
+        WHERE escopo = $1 AND chave = $2
+    "#;
+
+    let registro = sqlx::query_as::<_, Registro>(query)
+        .bind(escopo)
+        .bind(chave)
+        .fetch_one(pool)
+        .await?;
+
+    if registro.fingerprint != fingerprint {
+        return Err(ApiError::ConstraintViolation(format!(
+            "{} já usada com outra requisição",
+            HEADER
+        )));
+    }
+
+    let status = registro
+        .status
+        .and_then(|s| StatusCode::from_u16(s as u16).ok())
+        .ok_or_else(|| {
+            ApiError::Conflict(format!(
+                "Requisição com esta {} ainda está em processamento",
+                HEADER
+            ))
+        })?;
+
+    let mut resposta = HttpResponse::build(status);
+    resposta.insert_header((HEADER_REPETIDA, "true"));
+    if let Some(content_type) = registro.content_type {
+        resposta.insert_header((header::CONTENT_TYPE, content_type));
+    }
+    Ok(resposta.body(registro.corpo.unwrap_or_default()))
+}
+
+async fn liberar(pool: &PgPool, escopo: &str, chave: &str) -> Result<(), ApiError> {
+    sqlx::query("DELETE FROM public.idempotency_key WHERE escopo = $1 AND chave = $2")
+        .bind(escopo)
+        .bind(chave)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,677 @@
+use std::io::Cursor;
+
+use actix_multipart::{Multipart, MultipartError};
+use actix_web::web::{self, Bytes, BytesMut};
+use actix_web::HttpResponse;
+use chrono::NaiveDateTime;
+use futures_util::TryStreamExt;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader, Limits};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::armazenamento::Storage;
+use crate::auth::AuthUser;
+use crate::config::MidiaConfig;
+use crate::error::{ApiError, FieldViolation};
+
+// Nome do campo do formulário multipart com a imagem
+const CAMPO_ARQUIVO: &str = "arquivo";
+// Imagens maiores que isso (em pixels, por lado) são recusadas antes de decodificar
+const DIMENSAO_MAXIMA: u32 = 8000;
+// A miniatura cabe num quadrado deste tamanho, mantendo a proporção
+const LADO_MINIATURA: u32 = 320;
+const QUALIDADE_JPEG: u8 = 88;
+const QUALIDADE_JPEG_MINIATURA: u8 = 80;
+const MAXIMO_IMAGENS_PORTFOLIO: i64 = 10;
+
+// Formulário dos uploads; existe só para documentar o corpo multipart no Swagger
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct UploadImagem {
+    // Imagem JPEG, PNG ou WebP
+    #[schema(value_type = String, format = Binary)]
+    arquivo: Vec<u8>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Imagem {
+    url: String,
+    url_miniatura: String,
+    content_type: String,
+    largura: i32,
+    altura: i32,
+    tamanho_bytes: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImagemPortfolio {
+    id: i32,
+    #[serde(flatten)]
+    imagem: Imagem,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Avatar {
+    url: String,
+    url_miniatura: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct LinhaPortfolio {
+    id: i32,
+    chave: String,
+    chave_miniatura: String,
+    content_type: String,
+    tamanho_bytes: i32,
+    largura: i32,
+    altura: i32,
+    created_at: NaiveDateTime,
+}
+
+impl LinhaPortfolio {
+    fn resposta(self, storage: &dyn Storage) -> ImagemPortfolio {
+        ImagemPortfolio {
+            id: self.id,
+            imagem: Imagem {
+                url: storage.url(&self.chave),
+                url_miniatura: storage.url(&self.chave_miniatura),
+                content_type: self.content_type,
+                largura: self.largura,
+                altura: self.altura,
+                tamanho_bytes: self.tamanho_bytes,
+            },
+            created_at: self.created_at,
+        }
+    }
+}
+
+// Imagem já validada e reencodada, pronta para ser gravada
+struct ImagemProcessada {
+    conteudo: Bytes,
+    miniatura: Bytes,
+    formato: ImageFormat,
+    largura: u32,
+    altura: u32,
+}
+
+impl ImagemProcessada {
+    fn content_type(&self) -> &'static str {
+        self.formato.to_mime_type()
+    }
+
+    fn extensao(&self) -> &'static str {
+        match self.formato {
+            ImageFormat::Png => "png",
+            _ => "jpg",
+        }
+    }
+}
+
+pub fn avatar(
+    storage: &dyn Storage,
+    chave: Option<String>,
+    chave_miniatura: Option<String>,
+) -> Option<Avatar> {
+    match (chave, chave_miniatura) {
+        (Some(chave), Some(chave_miniatura)) => Some(Avatar {
+            url: storage.url(&chave),
+            url_miniatura: storage.url(&chave_miniatura),
+        }),
+        _ => None,
+    }
+}
+
+fn erro_multipart(e: MultipartError) -> ApiError {
+    match e {
+        MultipartError::ContentTypeMissing
+        | MultipartError::ContentTypeParse
+        | MultipartError::ContentTypeIncompatible => ApiError::UnsupportedMediaType(
+            "Envie a imagem num formulário multipart/form-data".to_string(),
+        ),
+        e => ApiError::BadRequest(format!("Formulário multipart inválido: {}", e)),
+    }
+}
+
+// Lê o campo `arquivo` do formulário sem passar do limite configurado
+async fn ler_arquivo(mut multipart: Multipart, limite: usize) -> Result<Bytes, ApiError> {
+    let mut arquivo = None;
+
+    while let Some(mut campo) = multipart.try_next().await.map_err(erro_multipart)? {
+        if campo.name() != Some(CAMPO_ARQUIVO) || arquivo.is_some() {
+            // Outros campos são ignorados, mas precisam ser consumidos
+            while campo.try_next().await.map_err(erro_multipart)?.is_some() {}
+            continue;
+        }
+
+        let mut conteudo = BytesMut::new();
+        while let Some(parte) = campo.try_next().await.map_err(erro_multipart)? {
+            if conteudo.len() + parte.len() > limite {
+                return Err(ApiError::PayloadTooLarge(format!(
+                    "A imagem deve ter no máximo {} bytes",
+                    limite
+                )));
+            }
+            conteudo.extend_from_slice(&parte);
+        }
+        arquivo = Some(conteudo.freeze());
+    }
+
+    arquivo.filter(|a| !a.is_empty()).ok_or_else(|| {
+        ApiError::Validation(vec![FieldViolation {
+            field: CAMPO_ARQUIVO.to_string(),
+            code: "required".to_string(),
+            message: "Envie a imagem no campo arquivo do formulário".to_string(),
+        }])
+    })
+}
+
+fn erro_imagem(e: ImageError) -> ApiError {
+    match e {
+        ImageError::Limits(_) => ApiError::PayloadTooLarge(format!(
+            "A imagem deve ter no máximo {0}x{0} pixels",
+            DIMENSAO_MAXIMA
+        )),
+        _ => ApiError::UnsupportedMediaType("Não foi possível ler a imagem".to_string()),
+    }
+}
+
+fn codificar(imagem: &DynamicImage, formato: ImageFormat, qualidade: u8) -> Result<Bytes, ApiError> {
+    let mut buffer = Vec::new();
+    let resultado = match formato {
+        ImageFormat::Png => imagem.write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png),
+        _ => imagem
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buffer, qualidade)),
+    };
+    resultado.map_err(|e| {
+        log::error!("Erro ao codificar imagem: {:?}", e);
+        ApiError::Internal
+    })?;
+    Ok(Bytes::from(buffer))
+}
+
+// Trabalho pesado de CPU: chamar dentro de web::block
+fn processar(conteudo: Bytes) -> Result<ImagemProcessada, ApiError> {
+    // O tipo vem dos bytes, não do Content-Type nem do nome do arquivo enviados pelo cliente
+    let formato = match infer::get(&conteudo).map(|tipo| tipo.mime_type()) {
+        Some("image/jpeg") => ImageFormat::Jpeg,
+        Some("image/png") => ImageFormat::Png,
+        Some("image/webp") => ImageFormat::WebP,
+        _ => {
+            return Err(ApiError::UnsupportedMediaType(
+                "Envie uma imagem JPEG, PNG ou WebP".to_string(),
+            ))
+        }
+    };
+
+    let mut limites = Limits::default();
+    limites.max_image_width = Some(DIMENSAO_MAXIMA);
+    limites.max_image_height = Some(DIMENSAO_MAXIMA);
+
+    let mut leitor = ImageReader::with_format(Cursor::new(&conteudo[..]), formato);
+    leitor.limits(limites);
+    let mut decoder = leitor.into_decoder().map_err(erro_imagem)?;
+    let orientacao = decoder.orientation().map_err(erro_imagem)?;
+    let mut imagem = DynamicImage::from_decoder(decoder).map_err(erro_imagem)?;
+    // A rotação do EXIF é aplicada aos pixels, já que os metadados não são mantidos
+    imagem.apply_orientation(orientacao);
+
+    // Sempre reencoda: descarta metadados (como a localização do EXIF) e qualquer
+    // conteúdo extra anexado ao arquivo. Transparência exige PNG; o resto vira JPEG
+    let formato = if imagem.color().has_alpha() {
+        ImageFormat::Png
+    } else {
+        ImageFormat::Jpeg
+    };
+    let miniatura = imagem.thumbnail(LADO_MINIATURA, LADO_MINIATURA);
+
+    Ok(ImagemProcessada {
+        conteudo: codificar(&imagem, formato, QUALIDADE_JPEG)?,
+        miniatura: codificar(&miniatura, formato, QUALIDADE_JPEG_MINIATURA)?,
+        formato,
+        largura: imagem.width(),
+        altura: imagem.height(),
+    })
+}
+
+async fn receber_imagem(
+    multipart: Multipart,
+    config: &MidiaConfig,
+) -> Result<ImagemProcessada, ApiError> {
+    let conteudo = ler_arquivo(multipart, config.tamanho_maximo_bytes).await?;
+    web::block(move || processar(conteudo))
+        .await
+        .map_err(|_| ApiError::Internal)?
+}
+
+// Grava a imagem e a miniatura; devolve as chaves das duas
+async fn gravar(
+    storage: &dyn Storage,
+    prefixo: &str,
+    imagem: &ImagemProcessada,
+) -> Result<(String, String), ApiError> {
+    let nome = uuid::Uuid::new_v4();
+    let chave = format!("{}/{}.{}", prefixo, nome, imagem.extensao());
+    let chave_miniatura = format!("{}/{}_miniatura.{}", prefixo, nome, imagem.extensao());
+
+    storage
+        .salvar(&chave, imagem.conteudo.clone(), imagem.content_type())
+        .await?;
+    if let Err(e) = storage
+        .salvar(&chave_miniatura, imagem.miniatura.clone(), imagem.content_type())
+        .await
+    {
+        remover_arquivos(storage, &[chave]).await;
+        return Err(e.into());
+    }
+
+    Ok((chave, chave_miniatura))
+}
+
+// Remove arquivos que não são mais referenciados; falhas só vão para o log
+pub async fn remover_arquivos(storage: &dyn Storage, chaves: &[String]) {
+    for chave in chaves {
+        if let Err(e) = storage.remover(chave).await {
+            log::warn!("Não foi possível remover o arquivo {}: {}", chave, e);
+        }
+    }
+}
+
+// PUT: Envia ou troca o avatar do usuário
+#[utoipa::path(
+    put,
+    path = "/usuarios/{id}/avatar",
+    params(
+        ("id" = i32, Path, description = "ID do usuário")
+    ),
+    request_body(content = UploadImagem, content_type = "multipart/form-data"),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Avatar salvo; o anterior é apagado", body = Imagem),
+        (status = 400, description = "Formulário multipart inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 404, description = "Usuário não encontrado", body = ProblemDetails),
+        (status = 413, description = "Arquivo ou dimensões acima do limite", body = ProblemDetails),
+        (status = 415, description = "Corpo não é multipart/form-data ou o arquivo não é uma imagem JPEG, PNG ou WebP válida", body = ProblemDetails),
+        (status = 422, description = "Campo arquivo ausente", body = ProblemDetails),
+        (status = 500, description = "Erro ao salvar avatar", body = ProblemDetails),
+        (status = 503, description = "Banco de dados ou armazenamento indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn enviar_avatar(
+    pool: web::Data<sqlx::PgPool>,
+    storage: web::Data<dyn Storage>,
+    config: web::Data<MidiaConfig>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    multipart: Multipart,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    auth.garantir_dono(id)?;
+
+    let imagem = receber_imagem(multipart, &config).await?;
+    let storage = storage.get_ref();
+    let (chave, chave_miniatura) = gravar(storage, &format!("avatar/{}", id), &imagem).await?;
+
+    let query = r#"
+        UPDATE public.users AS u
+        SET avatar_chave = $2, avatar_miniatura_chave = $3
+        FROM (
+            SELECT id, avatar_chave, avatar_miniatura_chave
+            FROM public.users
+            WHERE id = $1
+            FOR UPDATE
+        ) AS antigo
+        WHERE u.id = antigo.id
+        RETURNING antigo.avatar_chave, antigo.avatar_miniatura_chave
+    "#;
+
+    let antigo = sqlx::query_as::<_, (Option<String>, Option<String>)>(query)
+        .bind(id)
+        .bind(&chave)
+        .bind(&chave_miniatura)
+        .fetch_optional(pool.get_ref())
+        .await;
+
+    let novas = [chave.clone(), chave_miniatura.clone()];
+    match antigo {
+        Ok(Some((avatar, miniatura))) => {
+            let antigas: Vec<String> = avatar.into_iter().chain(miniatura).collect();
+            remover_arquivos(storage, &antigas).await;
+        }
+        Ok(None) => {
+            remover_arquivos(storage, &novas).await;
+            return Err(ApiError::NotFound("Usuário não encontrado".to_string()));
+        }
+        Err(e) => {
+            remover_arquivos(storage, &novas).await;
+            return Err(e.into());
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(Imagem {
+        url: storage.url(&chave),
+        url_miniatura: storage.url(&chave_miniatura),
+        content_type: imagem.content_type().to_string(),
+        largura: imagem.largura as i32,
+        altura: imagem.altura as i32,
+        tamanho_bytes: imagem.conteudo.len() as i32,
+    }))
+}
+
+// DELETE: Remove o avatar do usuário
+#[utoipa::path(
+    delete,
+    path = "/usuarios/{id}/avatar",
+    params(
+        ("id" = i32, Path, description = "ID do usuário")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Avatar removido"),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 404, description = "O usuário não tem avatar", body = ProblemDetails),
+        (status = 500, description = "Erro ao remover avatar", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn remover_avatar(
+    pool: web::Data<sqlx::PgPool>,
+    storage: web::Data<dyn Storage>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    auth.garantir_dono(id)?;
+
+    let query = r#"
+        UPDATE public.users AS u
+        SET avatar_chave = NULL, avatar_miniatura_chave = NULL
+        FROM (
+            SELECT id, avatar_chave, avatar_miniatura_chave
+            FROM public.users
+            WHERE id = $1 AND avatar_chave IS NOT NULL
+            FOR UPDATE
+        ) AS antigo
+        WHERE u.id = antigo.id
+        RETURNING antigo.avatar_chave, antigo.avatar_miniatura_chave
+    "#;
+
+    let (avatar, miniatura) = sqlx::query_as::<_, (Option<String>, Option<String>)>(query)
+        .bind(id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("O usuário não tem avatar".to_string()))?;
+
+    let antigas: Vec<String> = avatar.into_iter().chain(miniatura).collect();
+    remover_arquivos(storage.get_ref(), &antigas).await;
+
+    Ok(HttpResponse::Ok().json("Avatar removido com sucesso"))
+}
+
+async fn garantir_oferta(
+    executor: impl sqlx::PgExecutor<'_>,
+    id_users: i32,
+    id_sub_habilidade: i32,
+    travar: bool,
+) -> Result<(), ApiError> {
+    let query = format!(
+        r#"
+        SELECT 1 FROM public.usuario_sub_habilidade
+        WHERE id_users = $1 AND id_sub_habilidade = $2
+        {}
+        "#,
+        if travar { "FOR UPDATE" } else { "" }
+    );
+
+    sqlx::query(&query)
+        .bind(id_users)
+        .bind(id_sub_habilidade)
+        .fetch_optional(executor)
+        .await?
+        .map(|_| ())
+        .ok_or_else(|| ApiError::NotFound("Sub-habilidade do usuário não encontrada".to_string()))
+}
+
+// GET: Lista as imagens do portfólio de uma oferta
+#[utoipa::path(
+    get,
+    path = "/portfolio/{id_users}/{id_sub_habilidade}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário dono da oferta"),
+        ("id_sub_habilidade" = i32, Path, description = "ID da sub-habilidade oferecida")
+    ),
+    responses(
+        (status = 200, description = "Imagens da oferta, da mais antiga para a mais nova", body = [ImagemPortfolio]),
+        (status = 404, description = "Sub-habilidade do usuário não encontrada", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar portfólio", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_portfolio(
+    pool: web::Data<sqlx::PgPool>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let (id_users, id_sub_habilidade) = path.into_inner();
+    garantir_oferta(pool.get_ref(), id_users, id_sub_habilidade, false).await?;
+
+    let query = r#"
+        SELECT id, chave, chave_miniatura, content_type, tamanho_bytes, largura, altura, created_at
+        FROM public.portfolio_imagem
+        WHERE id_users = $1 AND id_sub_habilidade = $2
+        ORDER BY id
+    "#;
+
+    let imagens: Vec<ImagemPortfolio> = sqlx::query_as::<_, LinhaPortfolio>(query)
+        .bind(id_users)
+        .bind(id_sub_habilidade)
+        .fetch_all(pool.get_ref())
+        .await?
+        .into_iter()
+        .map(|linha| linha.resposta(storage.get_ref()))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(imagens))
+}
+
+// POST: Adiciona uma imagem ao portfólio de uma oferta do próprio usuário
+#[utoipa::path(
+    post,
+    path = "/portfolio/{id_users}/{id_sub_habilidade}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário dono da oferta"),
+        ("id_sub_habilidade" = i32, Path, description = "ID da sub-habilidade oferecida")
+    ),
+    request_body(content = UploadImagem, content_type = "multipart/form-data"),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Imagem adicionada", body = ImagemPortfolio),
+        (status = 400, description = "Formulário multipart inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 404, description = "Sub-habilidade do usuário não encontrada", body = ProblemDetails),
+        (status = 409, description = "A oferta já tem o máximo de imagens", body = ProblemDetails),
+        (status = 413, description = "Arquivo ou dimensões acima do limite", body = ProblemDetails),
+        (status = 415, description = "Corpo não é multipart/form-data ou o arquivo não é uma imagem JPEG, PNG ou WebP válida", body = ProblemDetails),
+        (status = 422, description = "Campo arquivo ausente", body = ProblemDetails),
+        (status = 500, description = "Erro ao salvar imagem", body = ProblemDetails),
+        (status = 503, description = "Banco de dados ou armazenamento indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn adicionar_imagem_portfolio(
+    pool: web::Data<sqlx::PgPool>,
+    storage: web::Data<dyn Storage>,
+    config: web::Data<MidiaConfig>,
+    auth: AuthUser,
+    path: web::Path<(i32, i32)>,
+    multipart: Multipart,
+) -> Result<HttpResponse, ApiError> {
+    let (id_users, id_sub_habilidade) = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    let imagem = receber_imagem(multipart, &config).await?;
+    let storage = storage.get_ref();
+
+    // A oferta fica travada até o INSERT para que uploads simultâneos não passem do limite
+    let mut tx = pool.begin().await?;
+    garantir_oferta(&mut *tx, id_users, id_sub_habilidade, true).await?;
+
+    let total = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM public.portfolio_imagem WHERE id_users = $1 AND id_sub_habilidade = $2",
+    )
+    .bind(id_users)
+    .bind(id_sub_habilidade)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if total >= MAXIMO_IMAGENS_PORTFOLIO {
+        return Err(ApiError::Conflict(format!(
+            "A oferta já tem o máximo de {} imagens",
+            MAXIMO_IMAGENS_PORTFOLIO
+        )));
+    }
+
+    let prefixo = format!("portfolio/{}/{}", id_users, id_sub_habilidade);
+    let (chave, chave_miniatura) = gravar(storage, &prefixo, &imagem).await?;
+
+    let query = r#"
+        INSERT INTO public.portfolio_imagem
+            (id_users, id_sub_habilidade, chave, chave_miniatura, content_type, tamanho_bytes, largura, altura)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, chave, chave_miniatura, content_type, tamanho_bytes, largura, altura, created_at
+    "#;
+
+    let inserida = sqlx::query_as::<_, LinhaPortfolio>(query)
+        .bind(id_users)
+        .bind(id_sub_habilidade)
+        .bind(&chave)
+        .bind(&chave_miniatura)
+        .bind(imagem.content_type())
+        .bind(imagem.conteudo.len() as i32)
+        .bind(imagem.largura as i32)
+        .bind(imagem.altura as i32)
+        .fetch_one(&mut *tx)
+        .await;
+
+    let linha = match inserida {
+        Ok(linha) => match tx.commit().await {
+            Ok(()) => linha,
+            Err(e) => {
+                remover_arquivos(storage, &[chave, chave_miniatura]).await;
+                return Err(e.into());
+            }
+        },
+        Err(e) => {
+            remover_arquivos(storage, &[chave, chave_miniatura]).await;
+            return Err(e.into());
+        }
+    };
+
+    Ok(HttpResponse::Created().json(linha.resposta(storage)))
+}
+
+// DELETE: Remove uma imagem do portfólio de uma oferta do próprio usuário
+#[utoipa::path(
+    delete,
+    path = "/portfolio/{id_users}/{id_sub_habilidade}/{id}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário dono da oferta"),
+        ("id_sub_habilidade" = i32, Path, description = "ID da sub-habilidade oferecida"),
+        ("id" = i32, Path, description = "ID da imagem")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Imagem removida"),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 404, description = "Imagem não encontrada", body = ProblemDetails),
+        (status = 500, description = "Erro ao remover imagem", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn remover_imagem_portfolio(
+    pool: web::Data<sqlx::PgPool>,
+    storage: web::Data<dyn Storage>,
+    auth: AuthUser,
+    path: web::Path<(i32, i32, i32)>,
+) -> Result<HttpResponse, ApiError> {
+    let (id_users, id_sub_habilidade, id) = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    let query = r#"
+        DELETE FROM public.portfolio_imagem
+        WHERE id = $1 AND id_users = $2 AND id_sub_habilidade = $3
+        RETURNING chave, chave_miniatura
+    "#;
+
+    let (chave, chave_miniatura) = sqlx::query_as::<_, (String, String)>(query)
+        .bind(id)
+        .bind(id_users)
+        .bind(id_sub_habilidade)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Imagem não encontrada".to_string()))?;
+
+    remover_arquivos(storage.get_ref(), &[chave, chave_miniatura]).await;
+
+    Ok(HttpResponse::Ok().json("Imagem removida com sucesso"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn png(imagem: DynamicImage) -> Bytes {
+        codificar(&imagem, ImageFormat::Png, QUALIDADE_JPEG).unwrap()
+    }
+
+    #[test]
+    fn recusa_arquivo_que_nao_e_imagem() {
+        let resultado = processar(Bytes::from_static(b"%PDF-1.7 nao sou uma imagem"));
+        assert!(matches!(resultado, Err(ApiError::UnsupportedMediaType(_))));
+    }
+
+    #[test]
+    fn recusa_imagem_corrompida() {
+        let mut conteudo = png(DynamicImage::ImageRgb8(RgbImage::new(4, 4))).to_vec();
+        conteudo.truncate(40);
+        let resultado = processar(Bytes::from(conteudo));
+        assert!(matches!(resultado, Err(ApiError::UnsupportedMediaType(_))));
+    }
+
+    #[test]
+    fn recusa_imagem_acima_da_dimensao_maxima() {
+        let conteudo = png(DynamicImage::ImageRgb8(RgbImage::new(DIMENSAO_MAXIMA + 1, 1)));
+        let resultado = processar(conteudo);
+        assert!(matches!(resultado, Err(ApiError::PayloadTooLarge(_))));
+    }
+
+    #[test]
+    fn reencoda_como_jpeg_e_gera_miniatura() {
+        let conteudo = png(DynamicImage::ImageRgb8(RgbImage::from_pixel(640, 480, Rgb([200, 10, 10]))));
+        let imagem = processar(conteudo).unwrap();
+
+        assert_eq!(imagem.formato, ImageFormat::Jpeg);
+        assert_eq!((imagem.largura, imagem.altura), (640, 480));
+        assert_eq!(infer::get(&imagem.conteudo).unwrap().mime_type(), "image/jpeg");
+
+        let miniatura = image::load_from_memory(&imagem.miniatura).unwrap();
+        assert_eq!((miniatura.width(), miniatura.height()), (LADO_MINIATURA, 240));
+    }
+
+    #[test]
+    fn mantem_png_quando_ha_transparencia() {
+        let conteudo = png(DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([0, 0, 0, 0]))));
+        let imagem = processar(conteudo).unwrap();
+
+        assert_eq!(imagem.formato, ImageFormat::Png);
+        assert_eq!(imagem.extensao(), "png");
+        assert_eq!(imagem.content_type(), "image/png");
+    }
+}
@@ -0,0 +1,245 @@
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::bloqueios;
+use crate::error::ApiError;
+use crate::paginacao;
+use crate::validacao;
+
+// Resumo das avaliações já reveladas do dono da oferta `u` (veja avaliacoes::resumo);
+// expõe av.media e av.total. Usado aqui e na busca
+pub const JOIN_AVALIACOES: &str = r#"
+    LEFT JOIN LATERAL (
+        SELECT ROUND(AVG(n.nota), 2)::float8 AS media, COUNT(DISTINCT a.id) AS total
+        FROM public.avaliacao AS a
+        LEFT JOIN public.avaliacao_nota AS n ON n.id_avaliacao = a.id
+        WHERE a.id_avaliado = u.id_users AND a.visivel_em <= now()
+    ) AS av ON TRUE
+"#;
+
+// Consulta base das ofertas (usuario_sub_habilidade) usada por /obter e /obter_tudo
+fn select_ofertas() -> String {
+    format!(
+        r#"
+        SELECT
+            u.id_users,
+            us.first_name,
+            CASE WHEN us.sobrenome_publico THEN us.last_name END AS last_name,
+            u.id_sub_habilidade,
+            s.nome AS nome_sub_habilidade,
+            u.descricao,
+            u.valor,
+            u.created_at,
+            av.media AS media_avaliacao,
+            av.total AS total_avaliacoes
+        FROM
+            public.usuario_sub_habilidade AS u
+        INNER JOIN
+            public.sub_habilidade AS s
+        ON
+            u.id_sub_habilidade = s.id
+        INNER JOIN
+            public.users AS us
+        ON
+            u.id_users = us.id
+        {}
+        WHERE TRUE
+        "#,
+        JOIN_AVALIACOES
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrdenarOfertas {
+    #[default]
+    CreatedAt,
+    Valor,
+    Nome,
+}
+
+impl OrdenarOfertas {
+    fn coluna(&self) -> &'static str {
+        match self {
+            OrdenarOfertas::CreatedAt => "u.created_at",
+            OrdenarOfertas::Valor => "u.valor",
+            // Sobrenome privado não entra na ordenação
+            OrdenarOfertas::Nome => {
+                "(CASE WHEN us.sobrenome_publico THEN CONCAT(us.first_name, ' ', us.last_name) ELSE us.first_name END)"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Direcao {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl Direcao {
+    fn sql(&self) -> (&'static str, &'static str) {
+        // (palavra do ORDER BY, operador de comparação do cursor)
+        match self {
+            Direcao::Asc => ("ASC", ">"),
+            Direcao::Desc => ("DESC", "<"),
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+#[validate(schema(function = "validar_faixa_valor"))]
+pub struct OfertasQuery {
+    // Itens por página (1 a 100, padrão 20)
+    #[validate(range(min = 1, max = 100, message = "limit deve estar entre 1 e 100"))]
+    #[serde(default = "paginacao::limit_padrao")]
+    pub limit: i64,
+    // Valor de `next_cursor` recebido na página anterior
+    pub after: Option<String>,
+    #[serde(default)]
+    pub ordenar: OrdenarOfertas,
+    #[serde(default)]
+    pub direcao: Direcao,
+    pub id_sub_habilidade: Option<i32>,
+    pub id_habilidade: Option<i32>,
+    pub cidade: Option<String>,
+    #[param(value_type = Option<f64>)]
+    pub valor_min: Option<Decimal>,
+    #[param(value_type = Option<f64>)]
+    pub valor_max: Option<Decimal>,
+}
+
+fn validar_faixa_valor(params: &OfertasQuery) -> Result<(), ValidationError> {
+    if let (Some(min), Some(max)) = (params.valor_min, params.valor_max) {
+        if min > max {
+            return Err(validacao::erro_no_campo(
+                "valor_min",
+                "range",
+                "valor_min não pode ser maior que valor_max",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Chave de ordenação do item: permite montar o cursor para Dados e DadosAll
+pub trait Oferta {
+    fn id_users(&self) -> i32;
+    fn id_sub_habilidade(&self) -> i32;
+    fn created_at(&self) -> NaiveDateTime;
+    fn valor(&self) -> Decimal;
+    fn nome_completo(&self) -> String;
+}
+
+#[derive(Serialize, Deserialize)]
+enum ValorOrdenacao {
+    CreatedAt(NaiveDateTime),
+    Valor(Decimal),
+    Nome(String),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CursorOferta {
+    ordenar: OrdenarOfertas,
+    direcao: Direcao,
+    valor: ValorOrdenacao,
+    id_users: i32,
+    id_sub_habilidade: i32,
+}
+
+// Mesmo valor da coluna de ordenação por nome: sem o sobrenome quando ele é privado
+pub fn nome_completo(first_name: &str, last_name: Option<&str>) -> String {
+    match last_name {
+        Some(last_name) => format!("{} {}", first_name, last_name),
+        None => first_name.to_string(),
+    }
+}
+
+pub fn cursor<O: Oferta>(params: &OfertasQuery, oferta: &O) -> CursorOferta {
+    CursorOferta {
+        ordenar: params.ordenar,
+        direcao: params.direcao,
+        valor: match params.ordenar {
+            OrdenarOfertas::CreatedAt => ValorOrdenacao::CreatedAt(oferta.created_at()),
+            OrdenarOfertas::Valor => ValorOrdenacao::Valor(oferta.valor()),
+            OrdenarOfertas::Nome => ValorOrdenacao::Nome(oferta.nome_completo()),
+        },
+        id_users: oferta.id_users(),
+        id_sub_habilidade: oferta.id_sub_habilidade(),
+    }
+}
+
+// Monta a consulta com filtros, keyset pagination e LIMIT limit + 1
+pub fn consulta(
+    params: &OfertasQuery,
+    id_users: Option<i32>,
+    id_leitor: Option<i32>,
+) -> Result<QueryBuilder<'static, Postgres>, ApiError> {
+    let cursor = paginacao::decodificar::<CursorOferta>(params.after.as_deref())?;
+    let mut qb = QueryBuilder::<Postgres>::new(select_ofertas());
+
+    if let Some(id_users) = id_users {
+        qb.push(" AND u.id_users = ").push_bind(id_users);
+    }
+    // Ofertas de quem tem bloqueio com o usuário autenticado não aparecem para ele
+    if let Some(id_leitor) = id_leitor {
+        bloqueios::filtrar(&mut qb, "u.id_users", id_leitor);
+    }
+    if let Some(id) = params.id_sub_habilidade {
+        qb.push(" AND u.id_sub_habilidade = ").push_bind(id);
+    }
+    if let Some(id) = params.id_habilidade {
+        qb.push(" AND s.id_habilidade = ").push_bind(id);
+    }
+    if let Some(cidade) = params.cidade.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+        // Cidade privada não pode ser descoberta pelo filtro
+        qb.push(" AND us.cidade_publica AND lower(us.city) = lower(")
+            .push_bind(cidade.to_string())
+            .push(")");
+    }
+    if let Some(min) = params.valor_min {
+        qb.push(" AND u.valor >= ").push_bind(min);
+    }
+    if let Some(max) = params.valor_max {
+        qb.push(" AND u.valor <= ").push_bind(max);
+    }
+
+    let coluna = params.ordenar.coluna();
+    let (direcao, operador) = params.direcao.sql();
+
+    if let Some(cursor) = cursor {
+        if cursor.ordenar != params.ordenar || cursor.direcao != params.direcao {
+            return Err(ApiError::BadRequest(
+                "Cursor gerado com outra ordenação".to_string(),
+            ));
+        }
+        qb.push(format!(" AND ({}, u.id_users, u.id_sub_habilidade) {} (", coluna, operador));
+        match cursor.valor {
+            ValorOrdenacao::CreatedAt(v) if params.ordenar == OrdenarOfertas::CreatedAt => {
+                qb.push_bind(v)
+            }
+            ValorOrdenacao::Valor(v) if params.ordenar == OrdenarOfertas::Valor => qb.push_bind(v),
+            ValorOrdenacao::Nome(v) if params.ordenar == OrdenarOfertas::Nome => qb.push_bind(v),
+            _ => return Err(ApiError::BadRequest("Cursor inválido".to_string())),
+        };
+        qb.push(", ")
+            .push_bind(cursor.id_users)
+            .push(", ")
+            .push_bind(cursor.id_sub_habilidade)
+            .push(")");
+    }
+
+    qb.push(format!(
+        " ORDER BY {coluna} {direcao}, u.id_users {direcao}, u.id_sub_habilidade {direcao}"
+    ));
+    qb.push(" LIMIT ").push_bind(params.limit + 1);
+
+    Ok(qb)
+}
@@ -0,0 +1,140 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use crate::error::ApiError;
+
+pub const LIMIT_PADRAO: i64 = 20;
+
+pub fn limit_padrao() -> i64 {
+    LIMIT_PADRAO
+}
+
+// Parâmetros comuns a todas as listagens paginadas
+#[derive(Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct PaginaQuery {
+    // Itens por página (1 a 100, padrão 20)
+    #[validate(range(min = 1, max = 100, message = "limit deve estar entre 1 e 100"))]
+    #[serde(default = "limit_padrao")]
+    pub limit: i64,
+    // Valor de `next_cursor` recebido na página anterior
+    pub after: Option<String>,
+}
+
+// Envelope padrão das listagens paginadas
+#[derive(Serialize, ToSchema)]
+#[aliases(
+    PaginaDados = Pagina<crate::Dados>,
+    PaginaDadosAll = Pagina<crate::DadosAll>,
+    PaginaLikes = Pagina<crate::LikeResumo>,
+    PaginaIds = Pagina<i32>,
+    PaginaBusca = Pagina<crate::busca::ResultadoBusca>,
+    PaginaSugestoes = Pagina<crate::sugestoes::Sugestao>,
+    PaginaConversas = Pagina<crate::chat::Conversa>,
+    PaginaMensagens = Pagina<crate::chat::Mensagem>,
+    PaginaSessoes = Pagina<crate::sessoes::Sessao>,
+    PaginaAvaliacoes = Pagina<crate::avaliacoes::Avaliacao>,
+    PaginaLancamentos = Pagina<crate::creditos::Lancamento>,
+    PaginaDisputas = Pagina<crate::custodia::Disputa>,
+    PaginaBloqueios = Pagina<crate::bloqueios::Bloqueio>,
+    PaginaDenuncias = Pagina<crate::denuncias::Denuncia>,
+    PaginaRecomendacoes = Pagina<crate::recomendacoes::Recomendacao>
+)]
+pub struct Pagina<T> {
+    pub items: Vec<T>,
+    // Ausente na última página
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Pagina<T> {
+    // `items` deve vir da consulta com LIMIT limit + 1; o item extra só indica que há próxima página
+    pub fn montar<C, F>(mut items: Vec<T>, limit: i64, chave: F) -> Result<Pagina<T>, ApiError>
+    where
+        C: Serialize,
+        F: Fn(&T) -> C,
+    {
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items.last().map(|ultimo| codificar(&chave(ultimo))).transpose()?
+        } else {
+            None
+        };
+        Ok(Pagina { items, next_cursor })
+    }
+}
+
+// O cursor é opaco para o cliente: JSON da chave do último item em base64 url-safe
+pub fn codificar<C: Serialize>(chave: &C) -> Result<String, ApiError> {
+    let json = serde_json::to_vec(chave).map_err(|e| {
+        log::error!("Erro ao serializar cursor: {:?}", e);
+        ApiError::Internal
+    })?;
+    Ok(URL_SAFE_NO_PAD.encode(json))
+}
+
+pub fn decodificar<C: DeserializeOwned>(cursor: Option<&str>) -> Result<Option<C>, ApiError> {
+    let Some(cursor) = cursor else {
+        return Ok(None);
+    };
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .map(Some)
+        .ok_or_else(|| ApiError::BadRequest("Cursor inválido".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_volta_para_a_mesma_chave() {
+        let chave = (String::from("Recife"), 42);
+        let cursor = codificar(&chave).unwrap();
+        assert!(!cursor.contains('=') && !cursor.contains('/') && !cursor.contains('+'));
+        let lido: Option<(String, i32)> = decodificar(Some(&cursor)).unwrap();
+        assert_eq!(lido, Some(chave));
+    }
+
+    #[test]
+    fn sem_cursor_comeca_do_inicio() {
+        let lido: Option<i32> = decodificar(None).unwrap();
+        assert_eq!(lido, None);
+    }
+
+    #[test]
+    fn recusa_cursor_invalido() {
+        // Base64 inválido, JSON inválido e JSON de outro formato
+        let json_de_texto = URL_SAFE_NO_PAD.encode(b"\"texto\"");
+        for cursor in ["não é base64!", "bm9wZQ", json_de_texto.as_str()] {
+            match decodificar::<i32>(Some(cursor)) {
+                Err(ApiError::BadRequest(mensagem)) => assert_eq!(mensagem, "Cursor inválido"),
+                _ => panic!("cursor {:?} deveria ser recusado", cursor),
+            }
+        }
+    }
+
+    #[test]
+    fn pagina_com_item_extra_tem_proximo_cursor() {
+        let pagina = Pagina::montar(vec![1, 2, 3, 4], 3, |&id| id).unwrap();
+        assert_eq!(pagina.items, [1, 2, 3]);
+        let cursor = pagina.next_cursor.expect("deveria haver próxima página");
+        assert_eq!(decodificar::<i32>(Some(&cursor)).unwrap(), Some(3));
+    }
+
+    #[test]
+    fn ultima_pagina_nao_tem_cursor() {
+        let pagina = Pagina::montar(vec![1, 2, 3], 3, |&id| id).unwrap();
+        assert_eq!(pagina.items, [1, 2, 3]);
+        assert!(pagina.next_cursor.is_none());
+
+        let vazia = Pagina::montar(Vec::<i32>::new(), 3, |&id| id).unwrap();
+        assert!(vazia.items.is_empty());
+        assert!(vazia.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,200 @@
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::auth::AuthUser;
+use crate::bloqueios;
+use crate::error::ApiError;
+use crate::paginacao::{self, Pagina, PaginaQuery};
+use crate::validacao;
+
+// Pesos de cada componente do score; ajuste aqui para recalibrar o ranking
+const PESO_NOVAS_SUB_HABILIDADES: f64 = 1.0;
+const PESO_RECIPROCIDADE: f64 = 1.0;
+const PESO_AFINIDADE_CATEGORIA: f64 = 0.5;
+const PESO_MESMA_CIDADE: f64 = 2.0;
+const PESO_CURTIU_VOCE: f64 = 3.0;
+const PESO_DESEJADAS_ATENDIDAS: f64 = 3.0;
+const PESO_DESEJADAS_RECIPROCAS: f64 = 3.0;
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ComponentesScore {
+    // Soma das prioridades das sub-habilidades desejadas pelo usuário que o candidato oferece
+    desejadas_atendidas: i64,
+    // Sub-habilidades do candidato que o usuário ainda não oferece
+    novas_sub_habilidades: i64,
+    // Sub-habilidades do usuário que o candidato não oferece (interesse em trocar de volta)
+    reciprocidade: i64,
+    // Novas sub-habilidades dentro de categorias em que o usuário já atua
+    afinidade_categoria: i64,
+    mesma_cidade: bool,
+    // O candidato já curtiu o usuário e aguarda resposta
+    curtiu_voce: bool,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Recomendacao {
+    id_users: i32,
+    first_name: String,
+    // null quando o candidato escondeu o campo
+    last_name: Option<String>,
+    city: Option<String>,
+    sub_habilidades: Vec<String>,
+    score: f64,
+    #[sqlx(flatten)]
+    componentes: ComponentesScore,
+}
+
+// GET: Retorna usuários ordenados por compatibilidade de habilidades com o usuário,
+// priorizando quem oferece o que ele deseja aprender e deseja o que ele oferece
+#[utoipa::path(
+    get,
+    path = "/recomendacoes/{id}",
+    params(
+        ("id" = i32, Path, description = "ID do usuário que receberá as recomendações"),
+        PaginaQuery
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Recomendações ordenadas por score", body = PaginaRecomendacoes),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar recomendações", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn buscar_recomendacoes(
+    pool: web::Data<sqlx::PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    params: web::Query<PaginaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+    validacao::validar(&*params)?;
+
+    // O cursor guarda score e ID do último item da página anterior
+    let after = paginacao::decodificar::<(f64, i32)>(params.after.as_deref())?;
+    let (score_after, id_after) = after.unzip();
+
+    // Ficam de fora: quem o usuário já curtiu, matches, pares do histórico e bloqueios.
+    // Quem curtiu o usuário continua elegível e ganha peso extra.
+    let query = format!(
+        r#"
+        WITH minhas AS (
+            SELECT ush.id_sub_habilidade, sh.id_habilidade
+            FROM public.usuario_sub_habilidade ush
+            JOIN public.sub_habilidade sh ON sh.id = ush.id_sub_habilidade
+            WHERE ush.id_users = $1
+        ),
+        desejadas AS (
+            SELECT id_sub_habilidade, prioridade
+            FROM public.usuario_sub_habilidade_desejada
+            WHERE id_users = $1
+        ),
+        candidatos AS (
+            SELECT
+                us.id AS id_users,
+                us.first_name,
+                CASE WHEN us.sobrenome_publico THEN us.last_name END AS last_name,
+                CASE WHEN us.cidade_publica THEN us.city END AS city,
+                us.cidade_publica,
+                us.desejadas_publicas,
+                array_agg(sh.nome ORDER BY sh.nome) AS sub_habilidades,
+                COUNT(*) FILTER (
+                    WHERE ush.id_sub_habilidade NOT IN (SELECT id_sub_habilidade FROM minhas)
+                ) AS novas_sub_habilidades,
+                COUNT(*) FILTER (
+                    WHERE ush.id_sub_habilidade NOT IN (SELECT id_sub_habilidade FROM minhas)
+                      AND sh.id_habilidade IN (SELECT id_habilidade FROM minhas)
+                ) AS afinidade_categoria,
+                COALESCE(SUM(d.prioridade), 0)::int8 AS desejadas_atendidas
+            FROM public.users us
+            JOIN public.usuario_sub_habilidade ush ON ush.id_users = us.id
+            JOIN public.sub_habilidade sh ON sh.id = ush.id_sub_habilidade
+            LEFT JOIN desejadas d ON d.id_sub_habilidade = ush.id_sub_habilidade
+            WHERE us.id <> $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM public.teste_match tm
+                  WHERE (tm.id_deu_like = $1 AND tm.id_liked = us.id)
+                     OR (tm.id_deu_like = us.id AND tm.id_liked = $1 AND tm.match = TRUE)
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM public.historico_match hm
+                  WHERE (hm.id1 = $1 AND hm.id2 = us.id) OR (hm.id1 = us.id AND hm.id2 = $1)
+              )
+              AND {sem_bloqueio}
+            GROUP BY us.id, us.first_name, us.last_name, us.city, us.sobrenome_publico, us.cidade_publica,
+                us.desejadas_publicas
+        ),
+        componentes AS (
+            SELECT
+                c.*,
+                (
+                    SELECT COUNT(*) FROM minhas m
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM public.usuario_sub_habilidade o
+                        WHERE o.id_users = c.id_users AND o.id_sub_habilidade = m.id_sub_habilidade
+                    )
+                ) AS reciprocidade,
+                -- Lista de desejos privada não conta para o score
+                (
+                    SELECT COALESCE(SUM(d.prioridade), 0)::int8
+                    FROM minhas m
+                    JOIN public.usuario_sub_habilidade_desejada d
+                      ON d.id_sub_habilidade = m.id_sub_habilidade AND d.id_users = c.id_users
+                    WHERE c.desejadas_publicas
+                ) AS desejadas_reciprocas,
+                -- Cidade privada não conta para o score
+                (
+                    c.cidade_publica
+                    AND c.city <> ''
+                    AND lower(c.city) = (SELECT lower(city) FROM public.users WHERE id = $1)
+                ) AS mesma_cidade,
+                EXISTS (
+                    SELECT 1 FROM public.teste_match tm
+                    WHERE tm.id_deu_like = c.id_users AND tm.id_liked = $1 AND tm.match = FALSE
+                ) AS curtiu_voce
+            FROM candidatos c
+        )
+        SELECT * FROM (
+            SELECT
+                *,
+                (
+                    novas_sub_habilidades * $2::float8
+                    + reciprocidade * $3::float8
+                    + afinidade_categoria * $4::float8
+                    + CASE WHEN mesma_cidade THEN $5::float8 ELSE 0 END
+                    + CASE WHEN curtiu_voce THEN $6::float8 ELSE 0 END
+                    + desejadas_atendidas * $7::float8
+                    + desejadas_reciprocas * $8::float8
+                ) AS score
+            FROM componentes
+        ) r
+        WHERE $9::float8 IS NULL OR r.score < $9 OR (r.score = $9 AND r.id_users > $10)
+        ORDER BY r.score DESC, r.id_users
+        LIMIT $11
+        "#,
+        sem_bloqueio = bloqueios::sem_bloqueio("$1", "us.id"),
+    );
+
+    let items = sqlx::query_as::<_, Recomendacao>(&query)
+        .bind(id_users)
+        .bind(PESO_NOVAS_SUB_HABILIDADES)
+        .bind(PESO_RECIPROCIDADE)
+        .bind(PESO_AFINIDADE_CATEGORIA)
+        .bind(PESO_MESMA_CIDADE)
+        .bind(PESO_CURTIU_VOCE)
+        .bind(PESO_DESEJADAS_ATENDIDAS)
+        .bind(PESO_DESEJADAS_RECIPROCAS)
+        .bind(score_after)
+        .bind(id_after)
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(items, params.limit, |r| (r.score, r.id_users))?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
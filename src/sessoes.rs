@@ -0,0 +1,1093 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::auth::AuthUser;
+use crate::custodia;
+use crate::error::ApiError;
+use crate::paginacao::{self, Pagina};
+use crate::validacao::{self, JsonValidado, Referencias};
+
+// Espaço das chaves de pg_advisory_xact_lock(bigint) usadas para travar a agenda de um usuário
+const TRAVA_AGENDA: i64 = 1 << 32;
+
+// Frequência com que a tarefa de fundo procura sessões com o prazo de contestação vencido
+const INTERVALO_ENCERRAMENTO: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum StatusSessao {
+    Proposta,
+    Confirmada,
+    Concluida,
+    Cancelada,
+    Recusada,
+    NaoCompareceu,
+}
+
+impl StatusSessao {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatusSessao::Proposta => "proposta",
+            StatusSessao::Confirmada => "confirmada",
+            StatusSessao::Concluida => "concluida",
+            StatusSessao::Cancelada => "cancelada",
+            StatusSessao::Recusada => "recusada",
+            StatusSessao::NaoCompareceu => "nao_compareceu",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum Modalidade {
+    Presencial,
+    Online,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Sessao {
+    pub id: i32,
+    pub id_proponente: i32,
+    pub id_convidado: i32,
+    // O que cada lado ensina; um dos dois pode ser nulo
+    pub id_sub_habilidade_proponente: Option<i32>,
+    pub id_sub_habilidade_convidado: Option<i32>,
+    pub inicio: DateTime<Utc>,
+    pub duracao_minutos: i32,
+    pub modalidade: Modalidade,
+    // Preenchido nas sessões presenciais
+    pub local: Option<String>,
+    // Preenchido nas sessões online
+    pub link: Option<String>,
+    pub status: StatusSessao,
+    // Quem precisa aceitar, recusar ou reagendar a proposta atual
+    pub id_aguardando: Option<i32>,
+    // Cada participante confirma a conclusão; a sessão fica concluida com as duas
+    pub concluida_proponente: bool,
+    pub concluida_convidado: bool,
+    // Participante que faltou, quando status = nao_compareceu
+    pub id_ausente: Option<i32>,
+    pub cancelada_por: Option<i32>,
+    // Justificativa da recusa ou do cancelamento
+    pub motivo: Option<String>,
+    // Aumenta a cada alteração de horário ou de status
+    pub sequencia: i32,
+    // Quando o segundo participante confirmou a conclusão
+    pub concluida_em: Option<DateTime<Utc>>,
+    pub created_at: NaiveDateTime,
+    pub atualizada_em: NaiveDateTime,
+}
+
+impl Sessao {
+    pub fn outro(&self, id_users: i32) -> i32 {
+        if self.id_proponente == id_users {
+            self.id_convidado
+        } else {
+            self.id_proponente
+        }
+    }
+
+    // Regras de cada transição que dependem só da sessão; match e agenda são conferidos no banco
+    fn permitir(
+        &self,
+        transicao: Transicao,
+        id_users: i32,
+        agora: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let comecou = agora >= self.inicio;
+        match transicao {
+            Transicao::Aceitar | Transicao::Recusar => {
+                exigir_status(self, &[StatusSessao::Proposta])?;
+                // Só quem foi convidado a responder pode aceitar ou recusar
+                if self.id_aguardando != Some(id_users) {
+                    return Err(ApiError::Forbidden(
+                        "A proposta aguarda a resposta do outro participante".to_string(),
+                    ));
+                }
+                if transicao == Transicao::Aceitar && comecou {
+                    return Err(ApiError::Conflict(
+                        "O horário proposto já passou; reagende a sessão".to_string(),
+                    ));
+                }
+            }
+            Transicao::Reagendar | Transicao::Cancelar => {
+                exigir_status(self, &[StatusSessao::Proposta, StatusSessao::Confirmada])?;
+                if self.status == StatusSessao::Confirmada && comecou {
+                    return Err(ApiError::Conflict(match transicao {
+                        Transicao::Cancelar => {
+                            "A sessão já começou; confirme a conclusão ou informe a ausência"
+                                .to_string()
+                        }
+                        _ => "A sessão já começou".to_string(),
+                    }));
+                }
+            }
+            Transicao::Concluir | Transicao::InformarAusencia => {
+                exigir_status(self, &[StatusSessao::Confirmada])?;
+                if !comecou {
+                    return Err(ApiError::Conflict("A sessão ainda não começou".to_string()));
+                }
+                // Uma confirmação de conclusão de qualquer lado contradiz a ausência
+                if transicao == Transicao::InformarAusencia
+                    && (self.concluida_proponente || self.concluida_convidado)
+                {
+                    return Err(ApiError::Conflict(
+                        "A conclusão desta sessão já foi confirmada".to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Ações dos participantes que mudam o status da sessão
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transicao {
+    Aceitar,
+    Recusar,
+    Reagendar,
+    Cancelar,
+    Concluir,
+    InformarAusencia,
+}
+
+const COLUNAS_SESSAO: &str = r#"
+    id, id_proponente, id_convidado, id_sub_habilidade_proponente, id_sub_habilidade_convidado,
+    inicio, duracao_minutos, modalidade, local, link, status, id_aguardando,
+    concluida_proponente, concluida_convidado, id_ausente, cancelada_por, motivo,
+    sequencia, concluida_em, created_at, atualizada_em
+"#;
+
+// Quando, por quanto tempo e onde; usado na proposta e no reagendamento
+#[derive(Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validar_agenda"))]
+pub struct Agenda {
+    // Data e hora com fuso (RFC 3339), ex.: 2024-05-10T14:00:00-03:00
+    inicio: DateTime<Utc>,
+    #[validate(range(min = 15, max = 480, message = "A duração deve estar entre 15 e 480 minutos"))]
+    duracao_minutos: i32,
+    modalidade: Modalidade,
+    // Obrigatório nas sessões presenciais
+    #[validate(
+        length(min = 1, max = 300, message = "O local deve ter entre 1 e 300 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    local: Option<String>,
+    // Obrigatório nas sessões online (http ou https)
+    #[validate(length(max = 500, message = "O link deve ter no máximo 500 caracteres"))]
+    link: Option<String>,
+}
+
+fn validar_agenda(agenda: &Agenda) -> Result<(), ValidationError> {
+    if agenda.inicio <= Utc::now() {
+        return Err(validacao::erro_no_campo(
+            "inicio",
+            "past",
+            "A sessão deve começar no futuro",
+        ));
+    }
+    match agenda.modalidade {
+        Modalidade::Presencial if agenda.local.is_none() => Err(validacao::erro_no_campo(
+            "local",
+            "required",
+            "Informe o local da sessão presencial",
+        )),
+        Modalidade::Online => {
+            let valido = agenda
+                .link
+                .as_deref()
+                .and_then(|link| reqwest::Url::parse(link.trim()).ok())
+                .is_some_and(|url| matches!(url.scheme(), "http" | "https"));
+            if valido {
+                Ok(())
+            } else {
+                Err(validacao::erro_no_campo(
+                    "link",
+                    "url",
+                    "Informe um link http(s) para a sessão online",
+                ))
+            }
+        }
+        Modalidade::Presencial => Ok(()),
+    }
+}
+
+impl Agenda {
+    // Guarda só o campo da modalidade escolhida
+    fn local(&self) -> Option<&str> {
+        match self.modalidade {
+            Modalidade::Presencial => self.local.as_deref().map(str::trim),
+            Modalidade::Online => None,
+        }
+    }
+
+    fn link(&self) -> Option<&str> {
+        match self.modalidade {
+            Modalidade::Online => self.link.as_deref().map(str::trim),
+            Modalidade::Presencial => None,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[validate(schema(function = "validar_proposta"))]
+pub struct ProporSessaoRequest {
+    // Usuário autenticado
+    #[validate(range(min = 1, message = "ID de usuário inválido"))]
+    id_proponente: i32,
+    #[validate(range(min = 1, message = "ID de usuário inválido"))]
+    id_convidado: i32,
+    // Sub-habilidade que o proponente ensina (uma oferta dele)
+    #[validate(range(min = 1, message = "ID de sub-habilidade inválido"))]
+    id_sub_habilidade_proponente: Option<i32>,
+    // Sub-habilidade que o convidado ensina (uma oferta dele)
+    #[validate(range(min = 1, message = "ID de sub-habilidade inválido"))]
+    id_sub_habilidade_convidado: Option<i32>,
+    #[validate(nested)]
+    agenda: Agenda,
+}
+
+fn validar_proposta(dados: &ProporSessaoRequest) -> Result<(), ValidationError> {
+    if dados.id_proponente == dados.id_convidado {
+        return Err(validacao::erro_no_campo(
+            "id_convidado",
+            "self_reference",
+            "O usuário não pode marcar uma sessão consigo mesmo",
+        ));
+    }
+    if dados.id_sub_habilidade_proponente.is_none() && dados.id_sub_habilidade_convidado.is_none() {
+        return Err(validacao::erro_no_campo(
+            "id_sub_habilidade_proponente",
+            "required",
+            "Informe o que ao menos um dos lados vai ensinar",
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct MotivoRequest {
+    #[validate(
+        length(min = 1, max = 1000, message = "O motivo deve ter entre 1 e 1000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    motivo: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct SessoesQuery {
+    // Todas quando ausente
+    status: Option<StatusSessao>,
+    // Itens por página (1 a 100, padrão 20)
+    #[validate(range(min = 1, max = 100, message = "limit deve estar entre 1 e 100"))]
+    #[serde(default = "paginacao::limit_padrao")]
+    limit: i64,
+    // Valor de `next_cursor` recebido na página anterior
+    after: Option<String>,
+}
+
+async fn garantir_match(
+    executor: impl sqlx::PgExecutor<'_>,
+    id1: i32,
+    id2: i32,
+) -> Result<(), ApiError> {
+    let query = r#"
+        SELECT EXISTS (
+            SELECT 1 FROM public.teste_match
+            WHERE match
+              AND ((id_deu_like = $1 AND id_liked = $2) OR (id_deu_like = $2 AND id_liked = $1))
+        )
+    "#;
+
+    let com_match = sqlx::query_scalar::<_, bool>(query)
+        .bind(id1)
+        .bind(id2)
+        .fetch_one(executor)
+        .await?;
+
+    if !com_match {
+        return Err(ApiError::Forbidden(
+            "Sessões só podem ser marcadas entre usuários com match".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Trava a sessão para a transição; só participantes podem alterá-la
+pub async fn travar_sessao(
+    tx: &mut sqlx::PgConnection,
+    id: i32,
+    auth: &AuthUser,
+) -> Result<Sessao, ApiError> {
+    let query = format!("SELECT {} FROM public.sessao_troca WHERE id = $1 FOR UPDATE", COLUNAS_SESSAO);
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Sessão não encontrada".to_string()))?;
+
+    auth.garantir_participante(sessao.id_proponente, sessao.id_convidado)?;
+    Ok(sessao)
+}
+
+pub fn exigir_status(sessao: &Sessao, permitidos: &[StatusSessao]) -> Result<(), ApiError> {
+    if !permitidos.contains(&sessao.status) {
+        return Err(ApiError::Conflict(format!(
+            "Operação não permitida para uma sessão com status {}",
+            sessao.status.as_str()
+        )));
+    }
+    Ok(())
+}
+
+// Recusa o horário se algum dos dois já tiver outra sessão confirmada que se sobreponha.
+// As travas serializam confirmações simultâneas envolvendo os mesmos usuários
+async fn garantir_agenda_livre(tx: &mut sqlx::PgConnection, sessao: &Sessao) -> Result<(), ApiError> {
+    sqlx::query("SELECT pg_advisory_xact_lock($1 + id) FROM unnest(ARRAY[$2, $3]::int4[]) AS id ORDER BY id")
+        .bind(TRAVA_AGENDA)
+        .bind(sessao.id_proponente)
+        .bind(sessao.id_convidado)
+        .execute(&mut *tx)
+        .await?;
+
+    let query = r#"
+        SELECT EXISTS (
+            SELECT 1 FROM public.sessao_troca
+            WHERE id <> $1
+              AND status = 'confirmada'
+              AND (id_proponente IN ($2, $3) OR id_convidado IN ($2, $3))
+              AND inicio < $4 + make_interval(mins => $5)
+              AND inicio + make_interval(mins => duracao_minutos) > $4
+        )
+    "#;
+
+    let ocupada = sqlx::query_scalar::<_, bool>(query)
+        .bind(sessao.id)
+        .bind(sessao.id_proponente)
+        .bind(sessao.id_convidado)
+        .bind(sessao.inicio)
+        .bind(sessao.duracao_minutos)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if ocupada {
+        return Err(ApiError::Conflict(
+            "Um dos participantes já tem outra sessão confirmada nesse horário".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// POST: Propõe uma sessão de troca a um usuário com quem há match
+#[utoipa::path(
+    post,
+    path = "/sessoes",
+    request_body = ProporSessaoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Sessão proposta, aguardando o convidado", body = Sessao),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário ou usuários sem match", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos ou sub-habilidade que o usuário não oferece", body = ProblemDetails),
+        (status = 500, description = "Erro ao propor sessão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn propor_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    dados: JsonValidado<ProporSessaoRequest>,
+) -> Result<HttpResponse, ApiError> {
+    auth.garantir_dono(dados.id_proponente)?;
+    garantir_match(pool.get_ref(), dados.id_proponente, dados.id_convidado).await?;
+
+    let mut referencias = Referencias::new(pool.get_ref());
+    if let Some(id_sub_habilidade) = dados.id_sub_habilidade_proponente {
+        referencias
+            .oferta("id_sub_habilidade_proponente", dados.id_proponente, id_sub_habilidade)
+            .await?;
+    }
+    if let Some(id_sub_habilidade) = dados.id_sub_habilidade_convidado {
+        referencias
+            .oferta("id_sub_habilidade_convidado", dados.id_convidado, id_sub_habilidade)
+            .await?;
+    }
+    referencias.verificar()?;
+
+    let query = format!(
+        r#"
+        INSERT INTO public.sessao_troca (
+            id_proponente, id_convidado, id_sub_habilidade_proponente, id_sub_habilidade_convidado,
+            inicio, duracao_minutos, modalidade, local, link, id_aguardando
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $2)
+        RETURNING {}
+        "#,
+        COLUNAS_SESSAO
+    );
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(dados.id_proponente)
+        .bind(dados.id_convidado)
+        .bind(dados.id_sub_habilidade_proponente)
+        .bind(dados.id_sub_habilidade_convidado)
+        .bind(dados.agenda.inicio)
+        .bind(dados.agenda.duracao_minutos)
+        .bind(dados.agenda.modalidade)
+        .bind(dados.agenda.local())
+        .bind(dados.agenda.link())
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Created().json(sessao))
+}
+
+// GET: Sessões do usuário, em ordem de início
+#[utoipa::path(
+    get,
+    path = "/sessoes/usuario/{id_users}",
+    params(
+        ("id_users" = i32, Path, description = "ID do usuário"),
+        SessoesQuery
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sessões em que o usuário participa", body = PaginaSessoes),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar sessões", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn listar_sessoes(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    params: web::Query<SessoesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+    validacao::validar(&*params)?;
+
+    let after = paginacao::decodificar::<(DateTime<Utc>, i32)>(params.after.as_deref())?;
+
+    let query = format!(
+        r#"
+        SELECT {}
+        FROM public.sessao_troca
+        WHERE (id_proponente = $1 OR id_convidado = $1)
+          AND ($2::varchar IS NULL OR status = $2)
+          AND ($3::timestamptz IS NULL OR (inicio, id) > ($3, $4))
+        ORDER BY inicio, id
+        LIMIT $5
+        "#,
+        COLUNAS_SESSAO
+    );
+
+    let sessoes = sqlx::query_as::<_, Sessao>(&query)
+        .bind(id_users)
+        .bind(params.status)
+        .bind(after.map(|(inicio, _)| inicio))
+        .bind(after.map(|(_, id)| id))
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(sessoes, params.limit, |s| (s.inicio, s.id))?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
+
+// GET: Detalhes de uma sessão
+#[utoipa::path(
+    get,
+    path = "/sessoes/{id}",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sessão", body = Sessao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar sessão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let query = format!("SELECT {} FROM public.sessao_troca WHERE id = $1", COLUNAS_SESSAO);
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(path.into_inner())
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Sessão não encontrada".to_string()))?;
+
+    auth.garantir_participante(sessao.id_proponente, sessao.id_convidado)?;
+    Ok(HttpResponse::Ok().json(sessao))
+}
+
+// POST: Aceita a proposta; a sessão fica confirmada
+#[utoipa::path(
+    post,
+    path = "/sessoes/{id}/aceitar",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sessão confirmada", body = Sessao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa, a proposta aguarda o outro participante ou o match foi desfeito", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sessão fora do status proposta, horário já passou ou conflita com outra sessão confirmada", body = ProblemDetails),
+        (status = 500, description = "Erro ao aceitar sessão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn aceitar_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool.begin().await?;
+    let sessao = travar_sessao(&mut tx, path.into_inner(), &auth).await?;
+    sessao.permitir(Transicao::Aceitar, auth.id, Utc::now())?;
+    garantir_match(&mut *tx, sessao.id_proponente, sessao.id_convidado).await?;
+    garantir_agenda_livre(&mut tx, &sessao).await?;
+
+    let query = format!(
+        r#"
+        UPDATE public.sessao_troca
+        SET status = 'confirmada', id_aguardando = NULL, confirmada_em = now(),
+            sequencia = sequencia + 1, atualizada_em = now()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        COLUNAS_SESSAO
+    );
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(sessao.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    custodia::reter(&mut tx, &sessao).await?;
+
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(sessao))
+}
+
+// POST: Recusa a proposta
+#[utoipa::path(
+    post,
+    path = "/sessoes/{id}/recusar",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    request_body = MotivoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sessão recusada", body = Sessao),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa ou a proposta aguarda o outro participante", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sessão fora do status proposta", body = ProblemDetails),
+        (status = 422, description = "Motivo inválido", body = ProblemDetails),
+        (status = 500, description = "Erro ao recusar sessão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn recusar_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    dados: JsonValidado<MotivoRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool.begin().await?;
+    let sessao = travar_sessao(&mut tx, path.into_inner(), &auth).await?;
+    sessao.permitir(Transicao::Recusar, auth.id, Utc::now())?;
+
+    let query = format!(
+        r#"
+        UPDATE public.sessao_troca
+        SET status = 'recusada', id_aguardando = NULL, motivo = $2,
+            sequencia = sequencia + 1, atualizada_em = now()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        COLUNAS_SESSAO
+    );
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(sessao.id)
+        .bind(dados.motivo.as_deref().map(str::trim))
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(sessao))
+}
+
+// PUT: Propõe outro horário ou local; a sessão volta a aguardar o outro participante
+#[utoipa::path(
+    put,
+    path = "/sessoes/{id}/reagendar",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    request_body = Agenda,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Nova proposta, aguardando o outro participante", body = Sessao),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão ou o match foi desfeito", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sessão já encerrada ou em andamento", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao reagendar sessão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn reagendar_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    agenda: JsonValidado<Agenda>,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool.begin().await?;
+    let sessao = travar_sessao(&mut tx, path.into_inner(), &auth).await?;
+    sessao.permitir(Transicao::Reagendar, auth.id, Utc::now())?;
+    garantir_match(&mut *tx, sessao.id_proponente, sessao.id_convidado).await?;
+    // A nova proposta precisa ser aceita de novo, e a confirmação volta a reservar os créditos
+    if sessao.status == StatusSessao::Confirmada {
+        custodia::estornar(&mut tx, sessao.id).await?;
+    }
+
+    let query = format!(
+        r#"
+        UPDATE public.sessao_troca
+        SET inicio = $2, duracao_minutos = $3, modalidade = $4, local = $5, link = $6,
+            status = 'proposta', id_aguardando = $7,
+            sequencia = sequencia + 1, atualizada_em = now()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        COLUNAS_SESSAO
+    );
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(sessao.id)
+        .bind(agenda.inicio)
+        .bind(agenda.duracao_minutos)
+        .bind(agenda.modalidade)
+        .bind(agenda.local())
+        .bind(agenda.link())
+        .bind(sessao.outro(auth.id))
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(sessao))
+}
+
+// POST: Cancela uma proposta ou uma sessão confirmada que ainda não começou
+#[utoipa::path(
+    post,
+    path = "/sessoes/{id}/cancelar",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    request_body = MotivoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sessão cancelada", body = Sessao),
+        (status = 400, description = "JSON malformado", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sessão já encerrada ou em andamento", body = ProblemDetails),
+        (status = 422, description = "Motivo inválido", body = ProblemDetails),
+        (status = 500, description = "Erro ao cancelar sessão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn cancelar_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    dados: JsonValidado<MotivoRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool.begin().await?;
+    let sessao = travar_sessao(&mut tx, path.into_inner(), &auth).await?;
+    sessao.permitir(Transicao::Cancelar, auth.id, Utc::now())?;
+    if sessao.status == StatusSessao::Confirmada {
+        custodia::cancelar(&mut tx, &sessao, auth.id).await?;
+    }
+
+    let query = format!(
+        r#"
+        UPDATE public.sessao_troca
+        SET status = 'cancelada', id_aguardando = NULL, cancelada_por = $2, motivo = $3,
+            sequencia = sequencia + 1, atualizada_em = now()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        COLUNAS_SESSAO
+    );
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(sessao.id)
+        .bind(auth.id)
+        .bind(dados.motivo.as_deref().map(str::trim))
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(sessao))
+}
+
+// POST: Confirma que a sessão aconteceu; concluída quando os dois confirmam
+#[utoipa::path(
+    post,
+    path = "/sessoes/{id}/concluir",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Confirmação registrada; status concluida se o outro participante já confirmou", body = Sessao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sessão não confirmada ou que ainda não começou", body = ProblemDetails),
+        (status = 500, description = "Erro ao concluir sessão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn concluir_sessao(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool.begin().await?;
+    let sessao = travar_sessao(&mut tx, path.into_inner(), &auth).await?;
+    sessao.permitir(Transicao::Concluir, auth.id, Utc::now())?;
+
+    let (proponente, convidado) = if auth.id == sessao.id_proponente {
+        (true, sessao.concluida_convidado)
+    } else {
+        (sessao.concluida_proponente, true)
+    };
+    let concluida = proponente && convidado;
+
+    let query = format!(
+        r#"
+        UPDATE public.sessao_troca
+        SET concluida_proponente = $2, concluida_convidado = $3,
+            status = CASE WHEN $4 THEN 'concluida' ELSE status END,
+            sequencia = sequencia + CASE WHEN $4 THEN 1 ELSE 0 END,
+            concluida_em = CASE WHEN $4 THEN now() ELSE concluida_em END,
+            atualizada_em = now()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        COLUNAS_SESSAO
+    );
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(sessao.id)
+        .bind(proponente)
+        .bind(convidado)
+        .bind(concluida)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if concluida {
+        custodia::concluir(&mut tx, &sessao).await?;
+    }
+
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(sessao))
+}
+
+// POST: Informa que o outro participante não compareceu
+#[utoipa::path(
+    post,
+    path = "/sessoes/{id}/ausencia",
+    params(
+        ("id" = i32, Path, description = "ID da sessão")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sessão encerrada como nao_compareceu, com o outro participante em id_ausente", body = Sessao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Usuário não participa da sessão", body = ProblemDetails),
+        (status = 404, description = "Sessão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sessão não confirmada, que ainda não começou ou cuja conclusão já foi confirmada", body = ProblemDetails),
+        (status = 500, description = "Erro ao registrar ausência", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn informar_ausencia(
+    pool: web::Data<PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut tx = pool.begin().await?;
+    let sessao = travar_sessao(&mut tx, path.into_inner(), &auth).await?;
+    sessao.permitir(Transicao::InformarAusencia, auth.id, Utc::now())?;
+
+    let query = format!(
+        r#"
+        UPDATE public.sessao_troca
+        SET status = 'nao_compareceu', id_ausente = $2,
+            sequencia = sequencia + 1, atualizada_em = now()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        COLUNAS_SESSAO
+    );
+
+    let sessao = sqlx::query_as::<_, Sessao>(&query)
+        .bind(sessao.id)
+        .bind(sessao.outro(auth.id))
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(HttpResponse::Ok().json(sessao))
+}
+
+// Tarefa de fundo: resolve a custódia das sessões cujo prazo de contestação venceu sem disputa.
+// Sessões confirmadas são dadas como concluídas; ausências informadas ficam valendo
+pub async fn encerrar_vencidas(pool: PgPool) {
+    let mut intervalo = tokio::time::interval(INTERVALO_ENCERRAMENTO);
+    loop {
+        intervalo.tick().await;
+        if let Err(e) = encerrar_pendentes(&pool).await {
+            log::error!("Falha ao encerrar sessões vencidas: {:?}", e);
+        }
+    }
+}
+
+async fn encerrar_pendentes(pool: &PgPool) -> Result<(), ApiError> {
+    let ids = sqlx::query_scalar::<_, i32>(
+        r#"
+        SELECT s.id
+        FROM public.sessao_troca s
+        WHERE s.inicio + make_interval(mins => s.duracao_minutos, hours => $1) <= now()
+          -- Confirmadas sem custódia (aulas gratuitas) também são encerradas, sem
+          -- transferir créditos; ausências só têm o que resolver com créditos retidos
+          AND (
+              s.status = 'confirmada'
+              OR (s.status = 'nao_compareceu' AND EXISTS (
+                  SELECT 1 FROM public.custodia_credito c
+                  WHERE c.id_sessao = s.id AND c.status = 'retida'
+              ))
+          )
+          -- Sessões disputadas ficam com a decisão da moderação
+          AND NOT EXISTS (SELECT 1 FROM public.disputa_sessao d WHERE d.id_sessao = s.id)
+        ORDER BY s.id
+        "#,
+    )
+    .bind(custodia::PRAZO_CONTESTACAO_HORAS as i32)
+    .fetch_all(pool)
+    .await?;
+
+    for id in ids {
+        let mut tx = pool.begin().await?;
+
+        // Outra instância ou uma requisição pode ter chegado antes
+        let query = format!(
+            "SELECT {} FROM public.sessao_troca WHERE id = $1 FOR UPDATE SKIP LOCKED",
+            COLUNAS_SESSAO
+        );
+        let Some(sessao) = sqlx::query_as::<_, Sessao>(&query)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            continue;
+        };
+
+        match sessao.status {
+            StatusSessao::Confirmada => {
+                let query = format!(
+                    r#"
+                    UPDATE public.sessao_troca
+                    SET status = 'concluida', concluida_em = now(),
+                        sequencia = sequencia + 1, atualizada_em = now()
+                    WHERE id = $1
+                    RETURNING {}
+                    "#,
+                    COLUNAS_SESSAO
+                );
+                let sessao = sqlx::query_as::<_, Sessao>(&query)
+                    .bind(sessao.id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                custodia::concluir(&mut tx, &sessao).await?;
+            }
+            StatusSessao::NaoCompareceu => custodia::resolver_ausencia(&mut tx, &sessao).await?,
+            _ => continue,
+        }
+
+        tx.commit().await?;
+        log::info!("Custódia da sessão {} resolvida por prazo", id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    const PROPONENTE: i32 = 1;
+    const CONVIDADO: i32 = 2;
+
+    const TODOS_OS_STATUS: [StatusSessao; 6] = [
+        StatusSessao::Proposta,
+        StatusSessao::Confirmada,
+        StatusSessao::Concluida,
+        StatusSessao::Cancelada,
+        StatusSessao::Recusada,
+        StatusSessao::NaoCompareceu,
+    ];
+
+    fn sessao(status: StatusSessao, inicio: DateTime<Utc>) -> Sessao {
+        Sessao {
+            id: 1,
+            id_proponente: PROPONENTE,
+            id_convidado: CONVIDADO,
+            id_sub_habilidade_proponente: Some(10),
+            id_sub_habilidade_convidado: None,
+            inicio,
+            duracao_minutos: 60,
+            modalidade: Modalidade::Online,
+            local: None,
+            link: Some("https://exemplo.com/sala".to_string()),
+            status,
+            id_aguardando: (status == StatusSessao::Proposta).then_some(CONVIDADO),
+            concluida_proponente: false,
+            concluida_convidado: false,
+            id_ausente: None,
+            cancelada_por: None,
+            motivo: None,
+            sequencia: 0,
+            concluida_em: None,
+            created_at: inicio.naive_utc(),
+            atualizada_em: inicio.naive_utc(),
+        }
+    }
+
+    fn agora() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    fn com_status(status: StatusSessao, comecou: bool) -> Sessao {
+        let inicio = if comecou {
+            agora() - Duration::minutes(30)
+        } else {
+            agora() + Duration::hours(1)
+        };
+        sessao(status, inicio)
+    }
+
+    fn status_aceitos(transicao: Transicao, comecou: bool) -> Vec<StatusSessao> {
+        TODOS_OS_STATUS
+            .into_iter()
+            .filter(|&status| {
+                com_status(status, comecou)
+                    .permitir(transicao, CONVIDADO, agora())
+                    .is_ok()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transicoes_permitidas_por_status_antes_do_inicio() {
+        use StatusSessao::*;
+        assert_eq!(status_aceitos(Transicao::Aceitar, false), [Proposta]);
+        assert_eq!(status_aceitos(Transicao::Recusar, false), [Proposta]);
+        assert_eq!(status_aceitos(Transicao::Reagendar, false), [Proposta, Confirmada]);
+        assert_eq!(status_aceitos(Transicao::Cancelar, false), [Proposta, Confirmada]);
+        assert!(status_aceitos(Transicao::Concluir, false).is_empty());
+        assert!(status_aceitos(Transicao::InformarAusencia, false).is_empty());
+    }
+
+    #[test]
+    fn transicoes_permitidas_por_status_depois_do_inicio() {
+        use StatusSessao::*;
+        assert!(status_aceitos(Transicao::Aceitar, true).is_empty());
+        assert_eq!(status_aceitos(Transicao::Recusar, true), [Proposta]);
+        assert_eq!(status_aceitos(Transicao::Reagendar, true), [Proposta]);
+        assert_eq!(status_aceitos(Transicao::Cancelar, true), [Proposta]);
+        assert_eq!(status_aceitos(Transicao::Concluir, true), [Confirmada]);
+        assert_eq!(status_aceitos(Transicao::InformarAusencia, true), [Confirmada]);
+    }
+
+    #[test]
+    fn status_encerrado_responde_conflito() {
+        let sessao = com_status(StatusSessao::Concluida, true);
+        match sessao.permitir(Transicao::Cancelar, PROPONENTE, agora()) {
+            Err(ApiError::Conflict(mensagem)) => {
+                assert_eq!(mensagem, "Operação não permitida para uma sessão com status concluida")
+            }
+            _ => panic!("cancelar uma sessão concluída deveria responder 409"),
+        }
+    }
+
+    #[test]
+    fn so_quem_foi_convidado_responde_a_proposta() {
+        let sessao = com_status(StatusSessao::Proposta, false);
+        for transicao in [Transicao::Aceitar, Transicao::Recusar] {
+            assert!(sessao.permitir(transicao, CONVIDADO, agora()).is_ok());
+            assert!(matches!(
+                sessao.permitir(transicao, PROPONENTE, agora()),
+                Err(ApiError::Forbidden(_))
+            ));
+        }
+        // Reagendar e cancelar valem para os dois lados
+        for transicao in [Transicao::Reagendar, Transicao::Cancelar] {
+            assert!(sessao.permitir(transicao, PROPONENTE, agora()).is_ok());
+        }
+    }
+
+    #[test]
+    fn ausencia_depois_de_confirmar_conclusao_e_recusada() {
+        let mut sessao = com_status(StatusSessao::Confirmada, true);
+        sessao.concluida_proponente = true;
+        assert!(sessao.permitir(Transicao::Concluir, CONVIDADO, agora()).is_ok());
+        assert!(matches!(
+            sessao.permitir(Transicao::InformarAusencia, CONVIDADO, agora()),
+            Err(ApiError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn inicio_exato_conta_como_comecada() {
+        let sessao = sessao(StatusSessao::Confirmada, agora());
+        assert!(sessao.permitir(Transicao::Concluir, PROPONENTE, agora()).is_ok());
+        assert!(sessao.permitir(Transicao::Cancelar, PROPONENTE, agora()).is_err());
+    }
+}
@@ -0,0 +1,543 @@
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use crate::auth::{AuthUser, Moderador};
+use crate::catalogo;
+use crate::error::{ApiError, FieldViolation};
+use crate::paginacao::{self, Pagina};
+use crate::validacao::{self, JsonValidado};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum StatusSugestao {
+    Pendente,
+    Aprovada,
+    Rejeitada,
+    Mesclada,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Sugestao {
+    id: i32,
+    id_users: i32,
+    id_habilidade: i32,
+    nome: String,
+    justificativa: String,
+    descricao_oferta: Option<String>,
+    #[serde(with = "rust_decimal::serde::float_option")]
+    #[schema(value_type = Option<f64>)]
+    valor_oferta: Option<Decimal>,
+    status: StatusSugestao,
+    // Sub-habilidade criada (aprovada) ou escolhida (mesclada)
+    id_sub_habilidade: Option<i32>,
+    // Explicação do moderador, obrigatória na rejeição
+    motivo: Option<String>,
+    id_moderador: Option<i32>,
+    created_at: NaiveDateTime,
+    decidida_em: Option<NaiveDateTime>,
+}
+
+// Oferta cadastrada automaticamente para o autor quando a sugestão é aceita
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct OfertaSugerida {
+    #[validate(
+        length(max = 1000, message = "A descrição deve ter no máximo 1000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    descricao: String,
+    #[validate(custom(function = "validacao::validar_valor"))]
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    valor: Decimal,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct SugestaoRequest {
+    #[validate(range(min = 1, message = "ID de habilidade inválido"))]
+    id_habilidade: i32,
+    #[validate(
+        length(min = 1, max = 100, message = "O nome deve ter entre 1 e 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    nome: String,
+    #[validate(length(max = 1000, message = "A justificativa deve ter no máximo 1000 caracteres"))]
+    #[serde(default)]
+    justificativa: String,
+    #[validate(nested)]
+    oferta: Option<OfertaSugerida>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AprovarSugestaoRequest {
+    // Corrige o nome sugerido (grafia, acentos) antes de criar a sub-habilidade
+    #[validate(
+        length(min = 1, max = 100, message = "O nome deve ter entre 1 e 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    nome: Option<String>,
+    // false ignora a oferta sugerida pelo autor
+    #[serde(default = "vincular_oferta_padrao")]
+    vincular_oferta: bool,
+}
+
+fn vincular_oferta_padrao() -> bool {
+    true
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct RejeitarSugestaoRequest {
+    #[validate(
+        length(min = 1, max = 1000, message = "O motivo deve ter entre 1 e 1000 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    motivo: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct MesclarSugestaoRequest {
+    // Sub-habilidade já existente que atende a sugestão
+    #[validate(range(min = 1, message = "ID de sub-habilidade inválido"))]
+    id_sub_habilidade: i32,
+    #[serde(default = "vincular_oferta_padrao")]
+    vincular_oferta: bool,
+}
+
+#[derive(Deserialize, IntoParams, Validate)]
+#[into_params(parameter_in = Query)]
+pub struct FilaQuery {
+    // Padrão: pendente
+    status: Option<StatusSugestao>,
+    // Itens por página (1 a 100, padrão 20)
+    #[validate(range(min = 1, max = 100, message = "limit deve estar entre 1 e 100"))]
+    #[serde(default = "paginacao::limit_padrao")]
+    limit: i64,
+    // Valor de `next_cursor` recebido na página anterior
+    after: Option<String>,
+}
+
+const SELECT_SUGESTAO: &str = r#"
+    SELECT
+        id, id_users, id_habilidade, nome, justificativa, descricao_oferta, valor_oferta,
+        status, id_sub_habilidade, motivo, id_moderador, created_at, decidida_em
+    FROM public.sugestao_sub_habilidade
+"#;
+
+// POST: Sugere uma nova sub-habilidade dentro de uma habilidade
+#[utoipa::path(
+    post,
+    path = "/sugestoes",
+    request_body = SugestaoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Sugestão registrada e aguardando moderação", body = Sugestao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 409, description = "Sub-habilidade já existe ou já há sugestão pendente com esse nome", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos ou habilidade inexistente/desativada", body = ProblemDetails),
+        (status = 500, description = "Erro ao registrar sugestão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn sugerir(
+    pool: web::Data<sqlx::PgPool>,
+    auth: AuthUser,
+    dados: JsonValidado<SugestaoRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let nome = dados.nome.trim();
+
+    let ativa = sqlx::query_scalar::<_, bool>("SELECT ativo FROM public.habilidade WHERE id = $1")
+        .bind(dados.id_habilidade)
+        .fetch_optional(pool.get_ref())
+        .await?;
+    let problema = match ativa {
+        None => Some(("not_found", "Habilidade não encontrada")),
+        Some(false) => Some(("inactive", "Habilidade desativada")),
+        Some(true) => None,
+    };
+    if let Some((code, mensagem)) = problema {
+        return Err(ApiError::Validation(vec![FieldViolation {
+            field: "id_habilidade".to_string(),
+            code: code.to_string(),
+            message: mensagem.to_string(),
+        }]));
+    }
+
+    // Se a sub-habilidade já existe, o usuário deve usá-la em vez de sugerir
+    catalogo::garantir_nome_sub_habilidade_livre(pool.get_ref(), dados.id_habilidade, nome, None)
+        .await?;
+
+    let pendente = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM public.sugestao_sub_habilidade
+            WHERE id_habilidade = $1
+              AND public.f_unaccent(lower(nome)) = public.f_unaccent(lower($2))
+              AND status = 'pendente'
+        )
+        "#,
+    )
+    .bind(dados.id_habilidade)
+    .bind(nome)
+    .fetch_one(pool.get_ref())
+    .await?;
+    if pendente {
+        return Err(ApiError::Conflict(format!(
+            "Já existe uma sugestão pendente chamada \"{}\" nesta habilidade",
+            nome
+        )));
+    }
+
+    let query = r#"
+        INSERT INTO public.sugestao_sub_habilidade
+            (id_users, id_habilidade, nome, justificativa, descricao_oferta, valor_oferta)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING
+            id, id_users, id_habilidade, nome, justificativa, descricao_oferta, valor_oferta,
+            status, id_sub_habilidade, motivo, id_moderador, created_at, decidida_em
+    "#;
+
+    // Sugestões simultâneas com o mesmo nome esbarram no índice parcial (23505 → 409)
+    let sugestao = sqlx::query_as::<_, Sugestao>(query)
+        .bind(auth.id)
+        .bind(dados.id_habilidade)
+        .bind(nome)
+        .bind(dados.justificativa.trim())
+        .bind(dados.oferta.as_ref().map(|o| o.descricao.trim()))
+        .bind(dados.oferta.as_ref().map(|o| o.valor))
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Created().json(sugestao))
+}
+
+// GET: Retorna as sugestões feitas por um usuário, das mais recentes para as mais antigas
+#[utoipa::path(
+    get,
+    path = "/sugestoes/usuario/{id_users}",
+    params(
+        ("id_users" = i32, Path, description = "ID do autor das sugestões")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sugestões do usuário e o andamento de cada uma", body = [Sugestao]),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar sugestões", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_minhas_sugestoes(
+    pool: web::Data<sqlx::PgPool>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id_users = path.into_inner();
+    auth.garantir_dono(id_users)?;
+
+    let query = format!("{} WHERE id_users = $1 ORDER BY id DESC", SELECT_SUGESTAO);
+
+    let sugestoes = sqlx::query_as::<_, Sugestao>(&query)
+        .bind(id_users)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(sugestoes))
+}
+
+// GET: Fila de moderação, das sugestões mais antigas para as mais novas
+#[utoipa::path(
+    get,
+    path = "/moderacao/sugestoes",
+    params(FilaQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sugestões no status pedido. Requer papel moderator ou admin.", body = PaginaSugestoes),
+        (status = 400, description = "Cursor inválido", body = ProblemDetails),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 422, description = "Parâmetros de paginação inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar sugestões", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_fila(
+    pool: web::Data<sqlx::PgPool>,
+    _moderador: Moderador,
+    params: web::Query<FilaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    validacao::validar(&*params)?;
+    let after = paginacao::decodificar::<i32>(params.after.as_deref())?;
+
+    let query = format!(
+        "{} WHERE status = $1 AND ($2::int4 IS NULL OR id > $2) ORDER BY id LIMIT $3",
+        SELECT_SUGESTAO
+    );
+
+    let sugestoes = sqlx::query_as::<_, Sugestao>(&query)
+        .bind(params.status.unwrap_or(StatusSugestao::Pendente))
+        .bind(after)
+        .bind(params.limit + 1)
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let pagina = Pagina::montar(sugestoes, params.limit, |s| s.id)?;
+    Ok(HttpResponse::Ok().json(pagina))
+}
+
+// Trava a sugestão para a decisão; só sugestões pendentes podem ser decididas
+async fn travar_pendente(
+    tx: &mut sqlx::PgConnection,
+    id: i32,
+) -> Result<Sugestao, ApiError> {
+    let query = format!("{} WHERE id = $1 FOR UPDATE", SELECT_SUGESTAO);
+
+    let sugestao = sqlx::query_as::<_, Sugestao>(&query)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Sugestão não encontrada".to_string()))?;
+
+    if sugestao.status != StatusSugestao::Pendente {
+        return Err(ApiError::Conflict("Sugestão já foi decidida".to_string()));
+    }
+    Ok(sugestao)
+}
+
+async fn decidir(
+    tx: &mut sqlx::PgConnection,
+    id: i32,
+    status: StatusSugestao,
+    id_sub_habilidade: Option<i32>,
+    motivo: Option<&str>,
+    id_moderador: i32,
+) -> Result<Sugestao, ApiError> {
+    let query = r#"
+        UPDATE public.sugestao_sub_habilidade
+        SET status = $2, id_sub_habilidade = $3, motivo = $4, id_moderador = $5, decidida_em = now()
+        WHERE id = $1
+        RETURNING
+            id, id_users, id_habilidade, nome, justificativa, descricao_oferta, valor_oferta,
+            status, id_sub_habilidade, motivo, id_moderador, created_at, decidida_em
+    "#;
+
+    let sugestao = sqlx::query_as::<_, Sugestao>(query)
+        .bind(id)
+        .bind(status)
+        .bind(id_sub_habilidade)
+        .bind(motivo)
+        .bind(id_moderador)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    Ok(sugestao)
+}
+
+// Cadastra a oferta sugerida para o autor; se ele já oferece a sub-habilidade, nada muda
+async fn vincular_oferta(
+    tx: &mut sqlx::PgConnection,
+    sugestao: &Sugestao,
+    id_sub_habilidade: i32,
+) -> Result<(), ApiError> {
+    let (Some(descricao), Some(valor)) = (&sugestao.descricao_oferta, sugestao.valor_oferta) else {
+        return Ok(());
+    };
+
+    let query = r#"
+        INSERT INTO public.usuario_sub_habilidade (id_users, id_sub_habilidade, descricao, valor)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (id_users, id_sub_habilidade) DO NOTHING
+    "#;
+
+    sqlx::query(query)
+        .bind(sugestao.id_users)
+        .bind(id_sub_habilidade)
+        .bind(descricao)
+        .bind(valor)
+        .execute(&mut *tx)
+        .await?;
+
+    Ok(())
+}
+
+// POST: Aprova a sugestão, cria a sub-habilidade e, se houver, a oferta do autor
+#[utoipa::path(
+    post,
+    path = "/moderacao/sugestoes/{id}/aprovar",
+    params(
+        ("id" = i32, Path, description = "ID da sugestão")
+    ),
+    request_body = AprovarSugestaoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sugestão aprovada; `id_sub_habilidade` aponta para a nova entrada. Requer papel moderator ou admin.", body = Sugestao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 404, description = "Sugestão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sugestão já decidida ou nome já existente no catálogo", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao aprovar sugestão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn aprovar_sugestao(
+    pool: web::Data<sqlx::PgPool>,
+    Moderador(moderador): Moderador,
+    path: web::Path<i32>,
+    dados: JsonValidado<AprovarSugestaoRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let mut tx = pool.begin().await?;
+
+    let sugestao = travar_pendente(&mut tx, id).await?;
+    let nome = dados.nome.as_deref().map(str::trim).unwrap_or(&sugestao.nome);
+
+    catalogo::garantir_nome_sub_habilidade_livre(&mut *tx, sugestao.id_habilidade, nome, None)
+        .await?;
+
+    let id_sub_habilidade = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO public.sub_habilidade (id_habilidade, nome) VALUES ($1, $2) RETURNING id",
+    )
+    .bind(sugestao.id_habilidade)
+    .bind(nome)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if dados.vincular_oferta {
+        vincular_oferta(&mut tx, &sugestao, id_sub_habilidade).await?;
+    }
+
+    let sugestao = decidir(
+        &mut tx,
+        id,
+        StatusSugestao::Aprovada,
+        Some(id_sub_habilidade),
+        None,
+        moderador.id,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    log::info!("Sugestão {} aprovada pelo usuário {}", id, moderador.id);
+    Ok(HttpResponse::Ok().json(sugestao))
+}
+
+// POST: Rejeita a sugestão com um motivo visível ao autor
+#[utoipa::path(
+    post,
+    path = "/moderacao/sugestoes/{id}/rejeitar",
+    params(
+        ("id" = i32, Path, description = "ID da sugestão")
+    ),
+    request_body = RejeitarSugestaoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sugestão rejeitada. Requer papel moderator ou admin.", body = Sugestao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 404, description = "Sugestão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sugestão já decidida", body = ProblemDetails),
+        (status = 422, description = "Motivo ausente", body = ProblemDetails),
+        (status = 500, description = "Erro ao rejeitar sugestão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn rejeitar_sugestao(
+    pool: web::Data<sqlx::PgPool>,
+    Moderador(moderador): Moderador,
+    path: web::Path<i32>,
+    dados: JsonValidado<RejeitarSugestaoRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let mut tx = pool.begin().await?;
+
+    travar_pendente(&mut tx, id).await?;
+    let sugestao = decidir(
+        &mut tx,
+        id,
+        StatusSugestao::Rejeitada,
+        None,
+        Some(dados.motivo.trim()),
+        moderador.id,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    log::info!("Sugestão {} rejeitada pelo usuário {}", id, moderador.id);
+    Ok(HttpResponse::Ok().json(sugestao))
+}
+
+// POST: Resolve a sugestão com uma sub-habilidade que já existe no catálogo
+#[utoipa::path(
+    post,
+    path = "/moderacao/sugestoes/{id}/mesclar",
+    params(
+        ("id" = i32, Path, description = "ID da sugestão")
+    ),
+    request_body = MesclarSugestaoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Sugestão mesclada a uma sub-habilidade existente. Requer papel moderator ou admin.", body = Sugestao),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Requer papel moderator ou admin", body = ProblemDetails),
+        (status = 404, description = "Sugestão não encontrada", body = ProblemDetails),
+        (status = 409, description = "Sugestão já decidida", body = ProblemDetails),
+        (status = 422, description = "Sub-habilidade inexistente", body = ProblemDetails),
+        (status = 500, description = "Erro ao mesclar sugestão", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn mesclar_sugestao(
+    pool: web::Data<sqlx::PgPool>,
+    Moderador(moderador): Moderador,
+    path: web::Path<i32>,
+    dados: JsonValidado<MesclarSugestaoRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let mut tx = pool.begin().await?;
+
+    let sugestao = travar_pendente(&mut tx, id).await?;
+
+    let existe = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM public.sub_habilidade WHERE id = $1)",
+    )
+    .bind(dados.id_sub_habilidade)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !existe {
+        return Err(ApiError::Validation(vec![FieldViolation {
+            field: "id_sub_habilidade".to_string(),
+            code: "not_found".to_string(),
+            message: "Sub-habilidade não encontrada".to_string(),
+        }]));
+    }
+
+    if dados.vincular_oferta {
+        vincular_oferta(&mut tx, &sugestao, dados.id_sub_habilidade).await?;
+    }
+
+    let sugestao = decidir(
+        &mut tx,
+        id,
+        StatusSugestao::Mesclada,
+        Some(dados.id_sub_habilidade),
+        None,
+        moderador.id,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    log::info!(
+        "Sugestão {} mesclada na sub-habilidade {} pelo usuário {}",
+        id,
+        dados.id_sub_habilidade,
+        moderador.id
+    );
+    Ok(HttpResponse::Ok().json(sugestao))
+}
@@ -0,0 +1,256 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::armazenamento::Storage;
+use crate::auth::{self, AuthUser};
+use crate::avaliacoes::{self, ResumoAvaliacoes};
+use crate::desejadas::NivelDesejado;
+use crate::error::ApiError;
+use crate::midia::{self, Avatar};
+use crate::validacao::{self, JsonValidado};
+
+// O que outros usuários podem ver; nome e bio são sempre públicos
+#[derive(Clone, Copy, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Privacidade {
+    sobrenome_publico: bool,
+    cidade_publica: bool,
+    desejadas_publicas: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct LinhaUsuario {
+    id: i32,
+    first_name: String,
+    last_name: String,
+    city: String,
+    bio: String,
+    avatar_chave: Option<String>,
+    avatar_miniatura_chave: Option<String>,
+    #[sqlx(flatten)]
+    privacidade: Privacidade,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct OfertaPerfil {
+    id_sub_habilidade: i32,
+    nome_sub_habilidade: String,
+    descricao: String,
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    valor: Decimal,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct DesejadaPerfil {
+    id_sub_habilidade: i32,
+    nome_sub_habilidade: String,
+    nivel_desejado: NivelDesejado,
+    prioridade: i16,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Perfil {
+    id: i32,
+    first_name: String,
+    // null quando o dono escondeu o campo
+    last_name: Option<String>,
+    city: Option<String>,
+    bio: String,
+    avatar: Option<Avatar>,
+    ofertas: Vec<OfertaPerfil>,
+    desejadas: Option<Vec<DesejadaPerfil>>,
+    avaliacao: ResumoAvaliacoes,
+    // Presente apenas quando o próprio dono consulta o perfil
+    #[serde(skip_serializing_if = "Option::is_none")]
+    privacidade: Option<Privacidade>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AtualizarPrivacidade {
+    sobrenome_publico: Option<bool>,
+    cidade_publica: Option<bool>,
+    desejadas_publicas: Option<bool>,
+}
+
+// Campos ausentes não mudam
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AtualizarPerfilRequest {
+    #[validate(
+        length(min = 1, max = 100, message = "O nome deve ter entre 1 e 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    first_name: Option<String>,
+    #[validate(
+        length(min = 1, max = 100, message = "O sobrenome deve ter entre 1 e 100 caracteres"),
+        custom(function = "validacao::validar_texto")
+    )]
+    last_name: Option<String>,
+    #[validate(length(max = 100, message = "A cidade deve ter no máximo 100 caracteres"))]
+    city: Option<String>,
+    #[validate(length(max = 1000, message = "A bio deve ter no máximo 1000 caracteres"))]
+    bio: Option<String>,
+    #[validate(nested)]
+    privacidade: Option<AtualizarPrivacidade>,
+}
+
+async fn montar_perfil(
+    pool: &sqlx::PgPool,
+    storage: &dyn Storage,
+    id: i32,
+    dono: bool,
+) -> Result<Perfil, ApiError> {
+    let query = r#"
+        SELECT
+            id, first_name, last_name, city, bio, avatar_chave, avatar_miniatura_chave,
+            sobrenome_publico, cidade_publica, desejadas_publicas
+        FROM public.users
+        WHERE id = $1
+    "#;
+
+    let usuario = sqlx::query_as::<_, LinhaUsuario>(query)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Usuário não encontrado".to_string()))?;
+
+    let query = r#"
+        SELECT u.id_sub_habilidade, s.nome AS nome_sub_habilidade, u.descricao, u.valor
+        FROM public.usuario_sub_habilidade u
+        JOIN public.sub_habilidade s ON s.id = u.id_sub_habilidade
+        WHERE u.id_users = $1
+        ORDER BY s.nome
+    "#;
+
+    let ofertas = sqlx::query_as::<_, OfertaPerfil>(query)
+        .bind(id)
+        .fetch_all(pool)
+        .await?;
+
+    let privacidade = usuario.privacidade;
+    let desejadas = if dono || privacidade.desejadas_publicas {
+        let query = r#"
+            SELECT d.id_sub_habilidade, s.nome AS nome_sub_habilidade, d.nivel_desejado, d.prioridade
+            FROM public.usuario_sub_habilidade_desejada d
+            JOIN public.sub_habilidade s ON s.id = d.id_sub_habilidade
+            WHERE d.id_users = $1
+            ORDER BY d.prioridade DESC, s.nome
+        "#;
+
+        Some(
+            sqlx::query_as::<_, DesejadaPerfil>(query)
+                .bind(id)
+                .fetch_all(pool)
+                .await?,
+        )
+    } else {
+        None
+    };
+
+    Ok(Perfil {
+        id: usuario.id,
+        first_name: usuario.first_name,
+        last_name: (dono || privacidade.sobrenome_publico).then_some(usuario.last_name),
+        city: (dono || privacidade.cidade_publica).then_some(usuario.city),
+        bio: usuario.bio,
+        avatar: midia::avatar(storage, usuario.avatar_chave, usuario.avatar_miniatura_chave),
+        ofertas,
+        desejadas,
+        avaliacao: avaliacoes::resumo(pool, id).await?,
+        privacidade: dono.then_some(privacidade),
+    })
+}
+
+// GET: Retorna o perfil de um usuário; campos privados só aparecem para o dono
+#[utoipa::path(
+    get,
+    path = "/usuarios/{id}",
+    params(
+        ("id" = i32, Path, description = "ID do usuário")
+    ),
+    security((), ("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Perfil público, ou completo quando o token é do próprio usuário", body = Perfil),
+        (status = 404, description = "Usuário não encontrado", body = ProblemDetails),
+        (status = 500, description = "Erro ao buscar perfil", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn obter_perfil(
+    pool: web::Data<sqlx::PgPool>,
+    storage: web::Data<dyn Storage>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    // O token é opcional: sem ele (ou inválido) a resposta é a visão pública
+    let dono = auth::autenticar(&req).is_ok_and(|usuario| usuario.id == id);
+
+    let perfil = montar_perfil(pool.get_ref(), storage.get_ref(), id, dono).await?;
+    Ok(HttpResponse::Ok().json(perfil))
+}
+
+// PUT: Atualiza o perfil e as preferências de privacidade do próprio usuário
+#[utoipa::path(
+    put,
+    path = "/usuarios/{id}",
+    params(
+        ("id" = i32, Path, description = "ID do usuário")
+    ),
+    request_body = AtualizarPerfilRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Perfil atualizado, na visão do dono", body = Perfil),
+        (status = 401, description = "Token ausente ou inválido", body = ProblemDetails),
+        (status = 403, description = "Operação sobre dados de outro usuário", body = ProblemDetails),
+        (status = 404, description = "Usuário não encontrado", body = ProblemDetails),
+        (status = 422, description = "Campos inválidos", body = ProblemDetails),
+        (status = 500, description = "Erro ao atualizar perfil", body = ProblemDetails),
+        (status = 503, description = "Banco de dados indisponível", body = ProblemDetails)
+    )
+)]
+pub async fn atualizar_perfil(
+    pool: web::Data<sqlx::PgPool>,
+    storage: web::Data<dyn Storage>,
+    auth: AuthUser,
+    path: web::Path<i32>,
+    dados: JsonValidado<AtualizarPerfilRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    auth.garantir_dono(id)?;
+
+    let privacidade = dados.privacidade.as_ref();
+
+    let query = r#"
+        UPDATE public.users
+        SET first_name = COALESCE($2, first_name),
+            last_name = COALESCE($3, last_name),
+            city = COALESCE($4, city),
+            bio = COALESCE($5, bio),
+            sobrenome_publico = COALESCE($6, sobrenome_publico),
+            cidade_publica = COALESCE($7, cidade_publica),
+            desejadas_publicas = COALESCE($8, desejadas_publicas)
+        WHERE id = $1
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(id)
+        .bind(dados.first_name.as_deref().map(str::trim))
+        .bind(dados.last_name.as_deref().map(str::trim))
+        .bind(dados.city.as_deref().map(str::trim))
+        .bind(dados.bio.as_deref().map(str::trim))
+        .bind(privacidade.and_then(|p| p.sobrenome_publico))
+        .bind(privacidade.and_then(|p| p.cidade_publica))
+        .bind(privacidade.and_then(|p| p.desejadas_publicas))
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound("Usuário não encontrado".to_string()));
+    }
+
+    let perfil = montar_perfil(pool.get_ref(), storage.get_ref(), id, true).await?;
+    Ok(HttpResponse::Ok().json(perfil))
+}
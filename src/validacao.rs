@@ -0,0 +1,359 @@
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use sqlx::PgPool;
+use std::ops::Deref;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::error::{ApiError, FieldViolation};
+
+// Limite de valor aceito para uma oferta de sub-habilidade
+pub const VALOR_MAXIMO: i64 = 100_000;
+
+// Casas decimais da coluna `valor` das ofertas
+const CASAS_VALOR: u32 = 2;
+
+// Extrator que desserializa o corpo JSON e aplica as regras declaradas com #[validate]
+pub struct JsonValidado<T>(pub T);
+
+impl<T> JsonValidado<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for JsonValidado<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for JsonValidado<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let dados = json.await?.into_inner();
+            validar(&dados)?;
+            Ok(JsonValidado(dados))
+        })
+    }
+}
+
+pub fn validar<T: Validate>(dados: &T) -> Result<(), ApiError> {
+    dados
+        .validate()
+        .map_err(|erros| ApiError::Validation(violacoes("", &erros)))
+}
+
+fn violacoes(prefixo: &str, erros: &ValidationErrors) -> Vec<FieldViolation> {
+    let mut lista = Vec::new();
+    for (campo, tipo) in erros.errors() {
+        let campo = match (prefixo.is_empty(), *campo) {
+            // Regras de schema (entre campos) ficam em "__all__" no validator
+            (true, "__all__") => String::new(),
+            (true, campo) => campo.to_string(),
+            (false, "__all__") => prefixo.to_string(),
+            (false, campo) => format!("{}.{}", prefixo, campo),
+        };
+        match tipo {
+            ValidationErrorsKind::Field(erros) => {
+                lista.extend(erros.iter().map(|e| violacao(&campo, e)))
+            }
+            ValidationErrorsKind::Struct(erros) => lista.extend(violacoes(&campo, erros)),
+            ValidationErrorsKind::List(itens) => {
+                for (indice, erros) in itens {
+                    lista.extend(violacoes(&format!("{}[{}]", campo, indice), erros));
+                }
+            }
+        }
+    }
+    lista.sort_by(|a, b| a.field.cmp(&b.field));
+    lista
+}
+
+fn violacao(campo: &str, erro: &ValidationError) -> FieldViolation {
+    // Regras de schema informam o campo afetado no parâmetro "field"
+    let campo = erro
+        .params
+        .get("field")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| campo.to_string());
+    FieldViolation {
+        field: campo,
+        code: erro.code.to_string(),
+        message: erro
+            .message
+            .as_ref()
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| format!("Valor inválido ({})", erro.code)),
+    }
+}
+
+pub fn erro(code: &'static str, mensagem: &'static str) -> ValidationError {
+    let mut erro = ValidationError::new(code);
+    erro.message = Some(mensagem.into());
+    erro
+}
+
+// Usado em regras de schema para apontar o campo responsável pela falha
+pub fn erro_no_campo(
+    campo: &'static str,
+    code: &'static str,
+    mensagem: &'static str,
+) -> ValidationError {
+    let mut erro = erro(code, mensagem);
+    erro.add_param("field".into(), &campo);
+    erro
+}
+
+pub fn validar_valor(valor: &Decimal) -> Result<(), ValidationError> {
+    if *valor < Decimal::ZERO || *valor > Decimal::from(VALOR_MAXIMO) {
+        return Err(erro("range", "O valor deve estar entre 0 e 100000"));
+    }
+    // O banco arredondaria em silêncio; melhor recusar
+    if valor.normalize().scale() > CASAS_VALOR {
+        return Err(erro("scale", "O valor deve ter no máximo duas casas decimais"));
+    }
+    Ok(())
+}
+
+pub fn validar_texto(texto: &str) -> Result<(), ValidationError> {
+    if texto.trim().is_empty() {
+        return Err(erro("blank", "O texto não pode estar em branco"));
+    }
+    Ok(())
+}
+
+// Checagens que dependem do banco; acumula as falhas para devolver todas de uma vez
+pub struct Referencias<'a> {
+    pool: &'a PgPool,
+    violacoes: Vec<FieldViolation>,
+}
+
+impl<'a> Referencias<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Referencias {
+            pool,
+            violacoes: Vec::new(),
+        }
+    }
+
+    pub async fn usuario(&mut self, campo: &str, id: i32) -> Result<&mut Self, ApiError> {
+        let existe = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM public.users WHERE id = $1)",
+        )
+        .bind(id)
+        .fetch_one(self.pool)
+        .await?;
+        if !existe {
+            self.falha(campo, "not_found", "Usuário não encontrado");
+        }
+        Ok(self)
+    }
+
+    pub async fn habilidade(&mut self, campo: &str, id: i32) -> Result<&mut Self, ApiError> {
+        let existe = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM public.habilidade WHERE id = $1)",
+        )
+        .bind(id)
+        .fetch_one(self.pool)
+        .await?;
+        if !existe {
+            self.falha(campo, "not_found", "Habilidade não encontrada");
+        }
+        Ok(self)
+    }
+
+    // Novas ofertas e desejos só podem usar sub-habilidades ativas (e de categorias ativas)
+    pub async fn sub_habilidade(&mut self, campo: &str, id: i32) -> Result<&mut Self, ApiError> {
+        let ativa = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT s.ativo AND h.ativo
+            FROM public.sub_habilidade s
+            JOIN public.habilidade h ON h.id = s.id_habilidade
+            WHERE s.id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+        match ativa {
+            None => self.falha(campo, "not_found", "Sub-habilidade não encontrada"),
+            Some(false) => self.falha(campo, "inactive", "Sub-habilidade desativada"),
+            Some(true) => {}
+        }
+        Ok(self)
+    }
+
+    // O usuário precisa ter uma oferta cadastrada da sub-habilidade
+    pub async fn oferta(
+        &mut self,
+        campo: &str,
+        id_users: i32,
+        id_sub_habilidade: i32,
+    ) -> Result<&mut Self, ApiError> {
+        let existe = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM public.usuario_sub_habilidade
+                WHERE id_users = $1 AND id_sub_habilidade = $2
+            )
+            "#,
+        )
+        .bind(id_users)
+        .bind(id_sub_habilidade)
+        .fetch_one(self.pool)
+        .await?;
+        if !existe {
+            self.falha(campo, "not_found", "O usuário não oferece esta sub-habilidade");
+        }
+        Ok(self)
+    }
+
+    fn falha(&mut self, campo: &str, code: &str, mensagem: &str) {
+        self.violacoes.push(FieldViolation {
+            field: campo.to_string(),
+            code: code.to_string(),
+            message: mensagem.to_string(),
+        });
+    }
+
+    pub fn verificar(&mut self) -> Result<(), ApiError> {
+        if self.violacoes.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::Validation(std::mem::take(&mut self.violacoes)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Validate)]
+    struct Item {
+        #[validate(custom(function = "validar_texto"))]
+        nome: String,
+    }
+
+    #[derive(Validate)]
+    #[validate(schema(function = "valor_exige_nome"))]
+    struct Pedido {
+        #[validate(custom(function = "validar_valor"))]
+        valor: Decimal,
+        #[validate(nested)]
+        itens: Vec<Item>,
+        #[validate(nested)]
+        principal: Option<Item>,
+    }
+
+    fn valor_exige_nome(pedido: &Pedido) -> Result<(), ValidationError> {
+        if pedido.valor > Decimal::ZERO && pedido.principal.is_none() {
+            return Err(erro_no_campo("principal", "required", "Informe o item principal"));
+        }
+        Ok(())
+    }
+
+    fn dec(valor: &str) -> Decimal {
+        valor.parse().unwrap()
+    }
+
+    fn item(nome: &str) -> Item {
+        Item {
+            nome: nome.to_string(),
+        }
+    }
+
+    fn violacoes_de(pedido: &Pedido) -> Vec<FieldViolation> {
+        match validar(pedido) {
+            Err(ApiError::Validation(lista)) => lista,
+            Err(outro) => panic!("erro inesperado: {}", outro),
+            Ok(()) => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn aceita_valor_nos_limites() {
+        assert!(validar_valor(&dec("0")).is_ok());
+        assert!(validar_valor(&dec("100000.00")).is_ok());
+    }
+
+    #[test]
+    fn recusa_valor_fora_da_faixa() {
+        assert_eq!(validar_valor(&dec("-0.01")).unwrap_err().code, "range");
+        assert_eq!(validar_valor(&dec("100000.01")).unwrap_err().code, "range");
+    }
+
+    #[test]
+    fn recusa_valor_com_mais_de_duas_casas() {
+        assert!(validar_valor(&dec("12.50")).is_ok());
+        assert!(validar_valor(&dec("12.5000")).is_ok());
+        assert_eq!(validar_valor(&dec("12.345")).unwrap_err().code, "scale");
+    }
+
+    #[test]
+    fn recusa_texto_em_branco() {
+        assert!(validar_texto("Rust").is_ok());
+        assert_eq!(validar_texto("").unwrap_err().code, "blank");
+        assert_eq!(validar_texto(" \t\n").unwrap_err().code, "blank");
+    }
+
+    #[test]
+    fn pedido_valido_nao_gera_violacoes() {
+        let pedido = Pedido {
+            valor: dec("10"),
+            itens: vec![item("a")],
+            principal: Some(item("b")),
+        };
+        assert!(validar(&pedido).is_ok());
+    }
+
+    #[test]
+    fn violacoes_aninhadas_usam_caminho_do_campo() {
+        let pedido = Pedido {
+            valor: dec("-1"),
+            itens: vec![item("a"), item(" ")],
+            principal: Some(item("")),
+        };
+        let lista = violacoes_de(&pedido);
+        let campos: Vec<_> = lista.iter().map(|v| (v.field.as_str(), v.code.as_str())).collect();
+        assert_eq!(
+            campos,
+            [("itens[1].nome", "blank"), ("principal.nome", "blank"), ("valor", "range")]
+        );
+        assert_eq!(lista[2].message, "O valor deve estar entre 0 e 100000");
+    }
+
+    #[test]
+    fn regra_de_schema_aponta_o_campo_informado() {
+        let pedido = Pedido {
+            valor: dec("5"),
+            itens: Vec::new(),
+            principal: None,
+        };
+        let lista = violacoes_de(&pedido);
+        assert_eq!(lista.len(), 1);
+        assert_eq!(lista[0].field, "principal");
+        assert_eq!(lista[0].code, "required");
+        assert_eq!(lista[0].message, "Informe o item principal");
+    }
+
+    #[test]
+    fn erro_sem_mensagem_usa_texto_padrao() {
+        let violacao = violacao("campo", &ValidationError::new("formato"));
+        assert_eq!(violacao.field, "campo");
+        assert_eq!(violacao.message, "Valor inválido (formato)");
+    }
+}